@@ -0,0 +1,187 @@
+use std::time::SystemTime;
+
+use chrono::{DateTime, Datelike, Local, Timelike, Weekday};
+use egui::{Align2, Color32, FontId, Rect, Sense, Ui, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::color::get_color;
+
+/// Days back the hourly timeline can show, history keeps hours for a week
+/// before rolling them up into days
+pub const TIMELINE_DAYS: i64 = 7;
+
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+const CELL_SIZE: f32 = 18.;
+const CELL_GAP: f32 = 2.;
+// room for the weekday names left of the grid and the hours above it
+const ROW_LABEL_WIDTH: f32 = 32.;
+const COLUMN_LABEL_HEIGHT: f32 = 16.;
+
+const CHART_HEIGHT: f32 = 140.;
+const BAR_WIDTH: f32 = 18.;
+const BAR_GAP: f32 = 3.;
+// room for the value above a bar and the hour below it
+const BAR_LABEL_HEIGHT: f32 = 16.;
+
+/// Presses by day of the week and hour of the day, in local time
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct PunchCard {
+    // [day from Monday][hour]
+    counts: [[u32; 24]; 7],
+}
+
+impl PunchCard {
+    pub fn record(&mut self, time: SystemTime) {
+        let time = DateTime::<Local>::from(time);
+        let day = time.weekday().num_days_from_monday() as usize;
+        self.counts[day][time.hour() as usize] += 1;
+    }
+
+    pub fn get(&self, weekday: Weekday, hour: u32) -> u32 {
+        self.counts[weekday.num_days_from_monday() as usize][hour as usize]
+    }
+
+    pub fn total(&self) -> u64 {
+        self.counts
+            .iter()
+            .flatten()
+            .map(|&count| u64::from(count))
+            .sum()
+    }
+
+    pub fn clear(&mut self) {
+        self.counts = Default::default();
+    }
+
+    /// Grid of a row per weekday and a column per hour, the busiest cell the
+    /// hottest
+    pub fn draw(&self, ui: &mut Ui, hue: f32) {
+        let text_color = Color32::from_rgb(32, 5, 64);
+        let step = CELL_SIZE + CELL_GAP;
+        let size = Vec2::new(
+            ROW_LABEL_WIDTH + 24. * step,
+            COLUMN_LABEL_HEIGHT + WEEKDAYS.len() as f32 * step,
+        );
+        let (rect, response) = ui.allocate_exact_size(size, Sense::hover());
+        let painter = ui.painter();
+        let max = self.counts.iter().flatten().copied().max().unwrap_or(0);
+        let grid_left = rect.left() + ROW_LABEL_WIDTH;
+        let grid_top = rect.top() + COLUMN_LABEL_HEIGHT;
+
+        for hour in (0..24).step_by(3) {
+            painter.text(
+                egui::pos2(grid_left + hour as f32 * step, rect.top()),
+                Align2::LEFT_TOP,
+                format!("{hour:02}"),
+                FontId::monospace(10.),
+                text_color,
+            );
+        }
+        for (row, weekday) in WEEKDAYS.into_iter().enumerate() {
+            let y = grid_top + row as f32 * step;
+            painter.text(
+                egui::pos2(rect.left(), y + CELL_SIZE / 2.),
+                Align2::LEFT_CENTER,
+                weekday.to_string(),
+                FontId::monospace(11.),
+                text_color,
+            );
+            for hour in 0..24 {
+                let count = self.get(weekday, hour);
+                // the busiest cell is as hot as 60 presses on the keyboard
+                let heat = match max {
+                    0 => 0,
+                    max => (u64::from(count) * 60 / u64::from(max)) as u32,
+                };
+                let cell = Rect::from_min_size(
+                    egui::pos2(grid_left + hour as f32 * step, y),
+                    Vec2::splat(CELL_SIZE),
+                );
+                painter.rect_filled(cell, 3., get_color(hue, heat));
+            }
+        }
+
+        if let Some(pos) = response.hover_pos() {
+            let hour = ((pos.x - grid_left) / step).floor();
+            let row = ((pos.y - grid_top) / step).floor();
+            if (0. ..24.).contains(&hour) && (0. ..7.).contains(&row) {
+                let weekday = WEEKDAYS[row as usize];
+                let hour = hour as u32;
+                response.on_hover_text_at_pointer(format!(
+                    "{weekday} {hour:02}:00–{:02}:00\n{} presses",
+                    hour + 1,
+                    self.get(weekday, hour)
+                ));
+            }
+        }
+    }
+}
+
+/// Bars of the presses in each hour of a day
+pub fn draw_hourly_chart(ui: &mut Ui, hours: &[u64; 24]) {
+    let text_color = Color32::from_rgb(32, 5, 64);
+    let bar_color = Color32::from_rgb(60, 110, 220);
+    let width = hours.len() as f32 * (BAR_WIDTH + BAR_GAP);
+    let (rect, _) = ui.allocate_exact_size(Vec2::new(width, CHART_HEIGHT), Sense::hover());
+    let painter = ui.painter();
+    let bar_bottom = rect.bottom() - BAR_LABEL_HEIGHT;
+    let max_height = CHART_HEIGHT - 2. * BAR_LABEL_HEIGHT;
+    let max = hours.iter().copied().max().unwrap_or(0);
+
+    for (hour, &count) in hours.iter().enumerate() {
+        let x = rect.left() + hour as f32 * (BAR_WIDTH + BAR_GAP);
+        if count > 0 {
+            let height = max_height * count as f32 / max as f32;
+            let bar = Rect::from_min_max(
+                egui::pos2(x, bar_bottom - height),
+                egui::pos2(x + BAR_WIDTH, bar_bottom),
+            );
+            painter.rect_filled(bar, 2., bar_color);
+            painter.text(
+                bar.center_top(),
+                Align2::CENTER_BOTTOM,
+                count.to_string(),
+                FontId::monospace(9.),
+                text_color,
+            );
+        }
+        painter.text(
+            egui::pos2(x + BAR_WIDTH / 2., bar_bottom + 3.),
+            Align2::CENTER_TOP,
+            format!("{hour:02}"),
+            FontId::monospace(9.),
+            text_color,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn presses_land_on_their_weekday_and_hour() {
+        let mut card = PunchCard::default();
+        // a Friday
+        let late = Local.with_ymd_and_hms(2026, 10, 16, 23, 40, 0).unwrap();
+        card.record(SystemTime::from(late));
+        card.record(SystemTime::from(late));
+        let morning = Local.with_ymd_and_hms(2026, 10, 17, 9, 5, 0).unwrap();
+        card.record(SystemTime::from(morning));
+
+        assert_eq!(card.get(Weekday::Fri, 23), 2);
+        assert_eq!(card.get(Weekday::Sat, 9), 1);
+        assert_eq!(card.get(Weekday::Sat, 23), 0);
+        assert_eq!(card.total(), 3);
+    }
+}
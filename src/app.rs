@@ -1,21 +1,38 @@
 use std::{
+    collections::HashMap,
     fs,
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::{mpsc, Arc, Mutex},
     thread,
+    time::{Duration, Instant, SystemTime},
 };
 
 use crate::{
+    activity::{self, PunchCard},
+    breaks::{BreakKind, BreakSettings, BreakTracker},
+    chord::PersistedChords,
     color,
+    event_store::{self, EventLog, EventStoreKind, RecordedEvent},
+    finger::{self, Finger, FingerMap, FingerOverrides},
+    history::{HistoryRange, PersistedBucket},
     key_box::KeyTextsLayout,
-    keyboard::{self, KeyboardType},
-    listen,
-    press_time_map::PressTimesMap,
+    keyboard::{self, HeatmapMode, KeyboardType},
+    kle, layout,
+    listen::{self, InputEvent},
+    logical_layout::LogicalLayout,
+    metrics::BigramMetrics,
+    mouse::{self, MouseInput},
+    ngram,
+    press_time_map::{is_text, HoldStats, PressTimesMap},
+    profile,
+    simulate::{self, SimulatedLayout, Simulation},
+    speed::{self, Speed},
+    storage::{self, ReadError, SnapshotKind},
     tray::{TrayCommand, TrayController},
     typing_log::TypingLog,
     window_visibility,
 };
-use chrono::prelude::DateTime;
+use chrono::{prelude::DateTime, Local, NaiveDate, NaiveDateTime, TimeDelta};
 use eframe::{App, CreationContext, Frame};
 use egui::{
     pos2, vec2, Align, Align2, Color32, Event, FontId, Layout, Margin, Rect, ScrollArea, Stroke,
@@ -25,34 +42,202 @@ use serde::{Deserialize, Serialize};
 
 const APP_ID: &str = "keyboard-heatmap";
 const STATE_FILE: &str = "heatmap-state.json";
+// daily backups and the snapshots taken before clearing or restoring
+const BACKUP_DIR: &str = "backups";
 
 pub struct State {
+    // profile the settings and counts belong to, not persisted
+    profile: String,
     keyboard_type: KeyboardType,
+    logical_layout: LogicalLayout,
+    heatmap_mode: HeatmapMode,
+    count_repeats: bool,
+    // span of history shown, not persisted
+    history_range: HistoryRange,
     hue: f32,
     start_time: DateTime<chrono::Local>,
     show_log_window: bool,
+    show_ngram_window: bool,
+    show_chord_window: bool,
+    show_metrics_window: bool,
+    show_simulation_window: bool,
+    simulated_layout: SimulatedLayout,
+    show_speed_window: bool,
+    show_activity_window: bool,
+    // day of the hourly timeline, in days before today, not persisted
+    timeline_days_back: i64,
+    show_break_window: bool,
+    break_settings: BreakSettings,
+    // follows the presses since the app started, not persisted
+    breaks: BreakTracker,
+    show_finger_chart: bool,
+    show_finger_tint: bool,
+    // finger given to a clicked key, not persisted
+    assign_finger: Option<Finger>,
+    finger_overrides: HashMap<KeyboardType, FingerOverrides>,
+    // pause that breaks key sequences for the n-gram stats
+    ngram_idle_gap_ms: u64,
+    autosave_interval_min: u64,
+    autosave_presses: u64,
+    // when the state file was last written, not persisted
+    last_saved: Option<DateTime<chrono::Local>>,
+    event_store: EventStoreKind,
     recording_enabled: bool,
+    // the state file is from a newer version and is not saved over, not
+    // persisted
+    read_only: bool,
 }
 
 #[derive(Serialize, Deserialize)]
 struct PersistedState {
+    // layout of the file, see `storage::STATE_VERSION`
+    #[serde(default)]
+    version: u32,
     keyboard_type: KeyboardType,
+    #[serde(default)]
+    logical_layout: LogicalLayout,
+    #[serde(default)]
+    heatmap_mode: HeatmapMode,
+    #[serde(default)]
+    count_repeats: bool,
     hue: f32,
     start_time: DateTime<chrono::Local>,
     #[serde(default)]
     show_log_window: bool,
+    #[serde(default)]
+    show_ngram_window: bool,
+    #[serde(default)]
+    show_chord_window: bool,
+    #[serde(default)]
+    show_metrics_window: bool,
+    #[serde(default)]
+    show_simulation_window: bool,
+    #[serde(default)]
+    simulated_layout: SimulatedLayout,
+    #[serde(default)]
+    show_speed_window: bool,
+    #[serde(default)]
+    show_activity_window: bool,
+    #[serde(default)]
+    show_break_window: bool,
+    #[serde(default)]
+    break_settings: BreakSettings,
+    #[serde(default)]
+    show_finger_chart: bool,
+    #[serde(default)]
+    show_finger_tint: bool,
+    #[serde(default)]
+    finger_overrides: Vec<(KeyboardType, Vec<(String, Finger)>)>,
+    #[serde(default = "default_ngram_idle_gap_ms")]
+    ngram_idle_gap_ms: u64,
+    #[serde(default = "default_autosave_interval_min")]
+    autosave_interval_min: u64,
+    #[serde(default = "default_autosave_presses")]
+    autosave_presses: u64,
+    #[serde(default)]
+    event_store: EventStoreKind,
     press_entries: Vec<(String, u32)>,
+    #[serde(default)]
+    char_entries: Vec<(String, u32)>,
+    #[serde(default)]
+    repeat_entries: Vec<(String, u32)>,
+    #[serde(default)]
+    hold_entries: Vec<(String, HoldStats)>,
+    #[serde(default)]
+    mouse_entries: Vec<(MouseInput, u32)>,
+    #[serde(default)]
+    error_entries: Vec<(String, u32)>,
+    #[serde(default)]
+    history: Vec<PersistedBucket>,
+    #[serde(default)]
+    ngram_entries: Vec<(Vec<String>, u32)>,
+    #[serde(default)]
+    chords: PersistedChords,
+    #[serde(default)]
+    punch_card: PunchCard,
+}
+
+fn default_ngram_idle_gap_ms() -> u64 {
+    ngram::DEFAULT_IDLE_GAP_MS
+}
+
+fn default_autosave_interval_min() -> u64 {
+    storage::DEFAULT_AUTOSAVE_INTERVAL_MIN
+}
+
+fn default_autosave_presses() -> u64 {
+    storage::DEFAULT_AUTOSAVE_PRESSES
 }
 
 struct KeyboardHeatmap {
     state: Arc<Mutex<State>>,
     press_map: Arc<Mutex<PressTimesMap>>,
     typing_log: Arc<Mutex<TypingLog>>,
+    events: Arc<Mutex<EventLog>>,
     tray_controller: Option<TrayController>,
     pending_screenshot_path: Option<PathBuf>,
-    viewport_keyboard_type: KeyboardType,
+    // `None` forces the viewport to be resized on the next layout change
+    viewport_size: Option<Vec2>,
     window_visible: bool,
     allow_root_close: bool,
+    import_report: Option<ImportReport>,
+    // why the saved data could not be loaded, shown until dismissed
+    load_warning: Option<String>,
+    // why the snapshot before clearing failed, asks whether to clear anyway
+    clear_failed: Option<String>,
+    // text of the custom range date fields while they are edited
+    custom_range_text: [String; 2],
+    // break the tray icon currently alerts about
+    alerted_break: Option<BreakKind>,
+    // total presses of each snapshot file, or why it could not be read
+    snapshot_presses: HashMap<PathBuf, Result<u64, String>>,
+    snapshot_preview: Option<SnapshotPreview>,
+    // counts of the shown history range from the event store
+    range_queries: RangeQueries,
+    // profile picked in the menu, switched to at the start of the next frame
+    pending_profile: Option<String>,
+    // name typed for a new profile
+    new_profile_name: String,
+    // why the typed profile could not be created, until the name is edited
+    new_profile_error: Option<String>,
+}
+
+/// Snapshot shown next to the current counts
+struct SnapshotPreview {
+    title: String,
+    press_map: PressTimesMap,
+}
+
+/// What a range query counts, results of other requests are stale
+#[derive(Clone, PartialEq)]
+struct RangeRequest {
+    bounds: (NaiveDateTime, NaiveDateTime),
+    event_store: EventStoreKind,
+    profile: String,
+    idle_gap: Duration,
+}
+
+struct RangeQuery {
+    request: RangeRequest,
+    queried: Instant,
+    // `None` when events are not kept or the query failed
+    press_map: Option<PressTimesMap>,
+}
+
+/// Range queries run on their own thread, so neither the UI nor the input
+/// worker waits for the event store
+struct RangeQueries {
+    last: Option<RangeQuery>,
+    // request of the query still running
+    running: Option<RangeRequest>,
+    sender: mpsc::Sender<RangeQuery>,
+    receiver: mpsc::Receiver<RangeQuery>,
+}
+
+/// Outcome of the last KLE import, shown until the user dismisses it
+enum ImportReport {
+    Imported { name: String, unbound: Vec<String> },
+    Failed(String),
 }
 
 impl eframe::App for KeyboardHeatmap {
@@ -62,6 +247,10 @@ impl eframe::App for KeyboardHeatmap {
             self.set_window_visibility(ctx, frame, false);
         }
 
+        let mut switched_profile = false;
+        if let Some(profile) = self.pending_profile.take() {
+            switched_profile = self.switch_profile(&profile);
+        }
         for command in self.poll_tray_commands() {
             match command {
                 TrayCommand::ToggleWindow => {
@@ -71,11 +260,17 @@ impl eframe::App for KeyboardHeatmap {
                     let mut state = self.state.lock().unwrap();
                     state.recording_enabled = !state.recording_enabled;
                 }
+                TrayCommand::SwitchProfile(profile) => {
+                    switched_profile |= self.switch_profile(&profile);
+                }
                 TrayCommand::ClearData => {
-                    let mut state = self.state.lock().unwrap();
-                    state.start_time = chrono::Local::now();
-                    self.press_map.lock().unwrap().map.clear();
-                    self.typing_log.lock().unwrap().clear();
+                    if let Err(err) = clear_data(
+                        &mut self.state.lock().unwrap(),
+                        &mut self.press_map.lock().unwrap(),
+                        &mut self.typing_log.lock().unwrap(),
+                    ) {
+                        self.clear_failed = Some(err.to_string());
+                    }
                 }
                 TrayCommand::Quit => {
                     self.allow_root_close = true;
@@ -85,7 +280,32 @@ impl eframe::App for KeyboardHeatmap {
         }
 
         let mut state = self.state.lock().unwrap();
-        let mut resize_viewport = false;
+        // another profile can have another keyboard
+        let mut resize_viewport = switched_profile;
+
+        let now = SystemTime::now();
+        let due_break = state.breaks.due(&state.break_settings, now);
+        if due_break != self.alerted_break {
+            if let Some(tray_controller) = &self.tray_controller {
+                if let Err(err) = tray_controller.set_alert(due_break.map(|kind| kind.message())) {
+                    eprintln!("failed to update tray alert: {err}");
+                }
+            }
+            self.alerted_break = due_break;
+        }
+
+        // counts from before the data was last cleared stay in the event store
+        // but are not shown
+        let start = state.start_time.naive_local();
+        let bounds = state.history_range.bounds(Local::now().date_naive());
+        let range_request = bounds.map(|(from, to)| RangeRequest {
+            bounds: (from.max(start), to),
+            event_store: state.event_store,
+            profile: state.profile.clone(),
+            idle_gap: Duration::from_millis(state.ngram_idle_gap_ms),
+        });
+        self.range_queries
+            .refresh(ctx, range_request.clone(), &self.events, &self.press_map);
 
         let frame = egui::Frame::new()
             .inner_margin(Margin::same(30))
@@ -94,8 +314,68 @@ impl eframe::App for KeyboardHeatmap {
         egui::CentralPanel::default().frame(frame).show(ctx, |ui| {
             let press_map = &mut self.press_map.lock().unwrap();
             let mut typing_log = self.typing_log.lock().unwrap();
-            let mut keyboard = keyboard::Keyboard::new(state.keyboard_type, state.hue);
-            keyboard.draw(press_map, ui);
+            let fingers = layout::layout_for(&state.keyboard_type).map(|layout| {
+                FingerMap::new(&layout, state.finger_overrides.get(&state.keyboard_type))
+            });
+            // keys are always tinted while fingers are being assigned
+            let tint = state.show_finger_tint || state.assign_finger.is_some();
+            let mut keyboard = keyboard::Keyboard::new(&state.keyboard_type, state.hue)
+                .with_legends(state.logical_layout)
+                .with_mode(state.heatmap_mode)
+                .with_repeats(state.count_repeats)
+                .with_fingers(fingers.clone().filter(|_| tint));
+            let stored = range_request
+                .as_ref()
+                .and_then(|request| self.range_queries.press_map(request));
+            let ranged = bounds
+                .filter(|_| stored.is_none())
+                .map(|(from, to)| press_map.in_range(from, to));
+            let shown = stored.or(ranged.as_ref()).unwrap_or(press_map);
+            let clicked = ui
+                .horizontal_top(|ui| {
+                    let clicked = keyboard.draw(shown, ui);
+                    ui.add_space(mouse::PANEL_GAP);
+                    mouse::MousePanel::new(state.hue).draw(shown, ui);
+                    clicked
+                })
+                .inner;
+            if let (Some(key), Some(finger)) = (clicked, state.assign_finger) {
+                let keyboard_type = state.keyboard_type.clone();
+                state
+                    .finger_overrides
+                    .entry(keyboard_type)
+                    .or_default()
+                    .insert(key, finger);
+            }
+            if let Some(fingers) = fingers.as_ref().filter(|_| state.show_finger_chart) {
+                ui.add_space(FINGER_CHART_SPACING);
+                fingers.loads(shown).draw_chart(ui);
+            }
+            let live_speed = typing_log.rolling_speed(SystemTime::now());
+            // the live speed decays while nothing is typed
+            if !live_speed.active.is_zero() {
+                ctx.request_repaint_after(Duration::from_secs(1));
+            }
+            let shown_presses = shown.total_presses();
+            let shown_repeats = shown.total_repeats();
+            let shown_correction_rate = shown.correction_rate();
+            let range_label = match state.history_range {
+                HistoryRange::AllTime => format!(
+                    "All time, since {}",
+                    state.start_time.format("%y-%m-%d %H:%M:%S")
+                ),
+                range if stored.is_some() => format!("{}, from events", range.description()),
+                // history buckets keep no hold times or modifiers
+                range => match state.heatmap_mode {
+                    HeatmapMode::HoldTime => {
+                        format!("{}, hold times of all time", range.description())
+                    }
+                    HeatmapMode::WhileHeld(_) => {
+                        format!("{}, shortcuts of all time", range.description())
+                    }
+                    _ => range.description(),
+                },
+            };
 
             ui.add_space(30.);
             ui.separator();
@@ -104,34 +384,226 @@ impl eframe::App for KeyboardHeatmap {
                 ui.menu_button("☰", |ui| {
                     ui.set_min_width(220.0);
 
+                    ui.label(format!("Range: {range_label}"));
+                    ui.label(format!("Total presses: {shown_presses}"));
+                    ui.label(format!("Auto-repeats: {shown_repeats}"));
                     ui.label(format!(
-                        "Recording since {}",
-                        state.start_time.format("%y-%m-%d %H:%M:%S")
-                    ));
-                    ui.label(format!("Total presses: {}", press_map.total_presses()));
+                        "Corrections: {:.1}% of presses",
+                        shown_correction_rate * 100.
+                    ))
+                    .on_hover_text("Backspace and Delete presses, auto-repeats included");
                     ui.label(format!(
                         "Log buffer: {}/{}",
                         typing_log.len(),
                         typing_log.capacity()
                     ));
                     ui.checkbox(&mut state.show_log_window, "Show log buffer window");
+                    ui.checkbox(&mut state.show_ngram_window, "Show n-gram window");
+                    ui.checkbox(&mut state.show_chord_window, "Show shortcut window");
+                    ui.checkbox(&mut state.show_metrics_window, "Show finger metrics window");
+                    ui.checkbox(&mut state.show_simulation_window, "Show layout simulation");
+                    ui.checkbox(&mut state.show_speed_window, "Show typing speed window");
+                    ui.checkbox(&mut state.show_activity_window, "Show activity window");
+                    ui.checkbox(&mut state.show_break_window, "Show break reminders window");
+                    ui.horizontal(|ui| {
+                        ui.label("N-gram idle gap");
+                        let gap = egui::DragValue::new(&mut state.ngram_idle_gap_ms)
+                            .range(100..=10_000)
+                            .suffix(" ms");
+                        if ui.add(gap).changed() {
+                            press_map.ngrams.idle_gap =
+                                Duration::from_millis(state.ngram_idle_gap_ms);
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Autosave every");
+                        ui.add(
+                            egui::DragValue::new(&mut state.autosave_interval_min)
+                                .range(1..=120)
+                                .suffix(" min"),
+                        );
+                        ui.label("or");
+                        ui.add(
+                            egui::DragValue::new(&mut state.autosave_presses)
+                                .range(100..=100_000)
+                                .speed(10)
+                                .suffix(" presses"),
+                        );
+                    });
+                    if let Some(last_saved) = state.last_saved {
+                        ui.label(format!("Last saved: {}", last_saved.format("%H:%M:%S")));
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Event history");
+                        let previous = state.event_store;
+                        egui::ComboBox::from_id_salt("event_store")
+                            .selected_text(state.event_store.description())
+                            .show_ui(ui, |ui| {
+                                for kind in EventStoreKind::ALL {
+                                    ui.selectable_value(
+                                        &mut state.event_store,
+                                        kind,
+                                        kind.description(),
+                                    );
+                                }
+                            });
+                        if state.event_store != previous {
+                            if let Err(err) = switch_event_store(
+                                &self.events,
+                                state.event_store,
+                                &state.profile,
+                                press_map,
+                            ) {
+                                eprintln!("failed to open the event store: {err}");
+                                state.event_store = previous;
+                            }
+                        }
+                    })
+                    .response
+                    .on_hover_text(
+                        "Keeps every event so history ranges are counted exactly, \
+                         hold times and n-grams included. Off keeps only the counts",
+                    );
                     ui.separator();
 
-                    ui.label("Keyboard");
-                    resize_viewport |= ui
-                        .radio_value(
-                            &mut state.keyboard_type,
-                            KeyboardType::QwertyMac,
-                            KeyboardType::QwertyMac.description(),
-                        )
-                        .changed();
+                    ui.menu_button(format!("Profile: {}", state.profile), |ui| {
+                        let profiles = profile::list_profiles(&app_data_dir());
+                        for name in &profiles {
+                            let active = *name == state.profile;
+                            if ui.selectable_label(active, name).clicked() && !active {
+                                self.pending_profile = Some(name.clone());
+                                ui.close();
+                            }
+                        }
+                        ui.separator();
+                        let problem = ui
+                            .horizontal(|ui| {
+                                let edit = ui.add(
+                                    egui::TextEdit::singleline(&mut self.new_profile_name)
+                                        .hint_text("New profile")
+                                        .desired_width(120.),
+                                );
+                                if edit.changed() {
+                                    self.new_profile_error = None;
+                                }
+                                let name = self.new_profile_name.trim().to_string();
+                                let taken = profiles
+                                    .iter()
+                                    .any(|profile| profile.to_lowercase() == name.to_lowercase());
+                                let problem = match profile::check_name(&name) {
+                                    Ok(()) if taken => Some(format!("{name} already exists")),
+                                    checked => checked.err(),
+                                };
+                                let create = ui
+                                    .add_enabled(problem.is_none(), egui::Button::new("Create"))
+                                    .on_hover_text("Starts with empty counts and default settings");
+                                if create.clicked() {
+                                    match profile::create_profile(&app_data_dir(), &name) {
+                                        Ok(()) => {
+                                            self.pending_profile = Some(name.clone());
+                                            self.new_profile_name.clear();
+                                            ui.close();
+                                        }
+                                        Err(err) => self.new_profile_error = Some(err),
+                                    }
+                                }
+                                // an empty field needs no explanation
+                                problem.filter(|_| !name.is_empty())
+                            })
+                            .inner;
+                        if let Some(problem) = self.new_profile_error.as_ref().or(problem.as_ref())
+                        {
+                            ui.colored_label(Color32::from_rgb(200, 40, 40), problem);
+                        }
+                    });
+
+                    ui.menu_button(
+                        format!("Keyboard: {}", state.keyboard_type.description()),
+                        |ui| {
+                            let user_layouts = layout::user_layout_names()
+                                .into_iter()
+                                .map(KeyboardType::Custom);
+                            for keyboard_type in
+                                KeyboardType::BUILT_IN.into_iter().chain(user_layouts)
+                            {
+                                let description = keyboard_type.description();
+                                resize_viewport |= ui
+                                    .radio_value(
+                                        &mut state.keyboard_type,
+                                        keyboard_type,
+                                        description,
+                                    )
+                                    .changed();
+                            }
+                        },
+                    );
+                    ui.menu_button(
+                        format!("Legends: {}", state.logical_layout.description()),
+                        |ui| {
+                            for logical_layout in LogicalLayout::ALL {
+                                ui.radio_value(
+                                    &mut state.logical_layout,
+                                    logical_layout,
+                                    logical_layout.description(),
+                                );
+                            }
+                        },
+                    );
+                    ui.menu_button(
+                        format!("Heatmap: {}", state.heatmap_mode.description()),
+                        |ui| {
+                            for mode in HeatmapMode::ALL {
+                                ui.radio_value(&mut state.heatmap_mode, mode, mode.description());
+                            }
+                        },
+                    );
+                    ui.checkbox(&mut state.count_repeats, "Count auto-repeat presses");
                     resize_viewport |= ui
-                        .radio_value(
-                            &mut state.keyboard_type,
-                            KeyboardType::Qwerty87,
-                            KeyboardType::Qwerty87.description(),
-                        )
+                        .checkbox(&mut state.show_finger_chart, "Show finger load chart")
                         .changed();
+                    ui.checkbox(&mut state.show_finger_tint, "Tint key borders by finger");
+                    ui.menu_button(
+                        format!(
+                            "Assign finger: {}",
+                            state
+                                .assign_finger
+                                .map_or("Off", |finger| finger.description())
+                        ),
+                        |ui| {
+                            ui.radio_value(&mut state.assign_finger, None, "Off");
+                            for finger in Finger::ALL {
+                                ui.radio_value(
+                                    &mut state.assign_finger,
+                                    Some(finger),
+                                    finger.description(),
+                                );
+                            }
+                            ui.separator();
+                            if ui.button("Reset to layout fingers").clicked() {
+                                let keyboard_type = state.keyboard_type.clone();
+                                state.finger_overrides.remove(&keyboard_type);
+                            }
+                        },
+                    );
+                    if ui.button("Reload layouts").clicked() {
+                        layout::forget_user_layouts();
+                        self.viewport_size = None;
+                        resize_viewport = true;
+                    }
+                    if ui.button("Import KLE layout").clicked() {
+                        match import_kle_layout() {
+                            Ok(Some((keyboard_type, report))) => {
+                                state.keyboard_type = keyboard_type;
+                                resize_viewport = true;
+                                self.import_report = Some(report);
+                            }
+                            Ok(None) => {}
+                            Err(err) => {
+                                self.import_report = Some(ImportReport::Failed(err.to_string()));
+                            }
+                        }
+                        ui.close();
+                    }
 
                     ui.separator();
                     ui.label("Theme Palette");
@@ -162,31 +634,148 @@ impl eframe::App for KeyboardHeatmap {
                         ui.close();
                     }
 
+                    ui.menu_button("Snapshots", |ui| {
+                        let snapshots = storage::list_snapshots(&backup_dir(&state.profile));
+                        if snapshots.is_empty() {
+                            ui.label("No snapshots yet");
+                        }
+                        for snapshot in snapshots {
+                            let presses = self
+                                .snapshot_presses
+                                .entry(snapshot.path.clone())
+                                .or_insert_with(|| snapshot_presses(&snapshot.path))
+                                .clone();
+                            let title = format!(
+                                "{} · {}",
+                                snapshot.taken.format("%Y-%m-%d %H:%M"),
+                                snapshot.kind.description()
+                            );
+                            ui.horizontal(|ui| {
+                                ui.label(&title);
+                                match &presses {
+                                    Ok(presses) => ui.label(format!("{presses} presses")),
+                                    Err(err) => ui.colored_label(
+                                        Color32::from_rgb(200, 40, 40),
+                                        format!("unreadable: {err}"),
+                                    ),
+                                };
+                                let readable = presses.is_ok();
+                                if ui
+                                    .add_enabled(readable, egui::Button::new("Preview"))
+                                    .clicked()
+                                {
+                                    match read_state_file(&snapshot.path) {
+                                        Ok(Some(saved)) => {
+                                            self.snapshot_preview = Some(SnapshotPreview {
+                                                title: title.clone(),
+                                                press_map: state_from_persisted(
+                                                    saved,
+                                                    &state.profile,
+                                                )
+                                                .1,
+                                            });
+                                            ui.close();
+                                        }
+                                        // the error is shown in place of the presses
+                                        Ok(None) => {
+                                            self.snapshot_presses.insert(
+                                                snapshot.path.clone(),
+                                                Err(SNAPSHOT_GONE.to_string()),
+                                            );
+                                        }
+                                        Err(err) => {
+                                            self.snapshot_presses
+                                                .insert(snapshot.path.clone(), Err(err.into()));
+                                        }
+                                    }
+                                }
+                                if ui
+                                    .add_enabled(readable, egui::Button::new("Restore"))
+                                    .on_hover_text("The current data is snapshotted first")
+                                    .clicked()
+                                {
+                                    match restore_snapshot(&mut state, press_map, &snapshot.path) {
+                                        Ok(()) => {
+                                            typing_log.clear();
+                                            resize_viewport = true;
+                                        }
+                                        Err(err) => {
+                                            self.load_warning = Some(format!(
+                                                "The snapshot {} could not be restored: {err}",
+                                                snapshot.path.display()
+                                            ));
+                                        }
+                                    }
+                                    ui.close();
+                                }
+                            });
+                        }
+                    });
                     if ui.button("Clear data").clicked() {
-                        state.start_time = chrono::Local::now();
-                        press_map.map.clear();
-                        typing_log.clear();
+                        if let Err(err) = clear_data(&mut state, press_map, &mut typing_log) {
+                            self.clear_failed = Some(err.to_string());
+                        }
                         ui.close();
                     }
                 });
 
-                ui.label(format!("{} presses", press_map.total_presses()));
+                ui.label(format!("{shown_presses} presses"));
+                ui.separator();
+                let wpm = if live_speed.is_measurable() {
+                    format!("{:.0} WPM", live_speed.wpm())
+                } else {
+                    "– WPM".to_string()
+                };
+                ui.label(wpm).on_hover_text(speed_label(live_speed));
                 if !state.recording_enabled {
                     ui.separator();
                     ui.label("Paused");
                 }
+                if let Some(finger) = state.assign_finger {
+                    ui.separator();
+                    if ui.button("Done").clicked() {
+                        state.assign_finger = None;
+                    }
+                    ui.label(format!("Click keys to assign the {}", finger.description()));
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_salt("history_range")
+                        .selected_text(state.history_range.description())
+                        .show_ui(ui, |ui| {
+                            for range in HistoryRange::PRESETS {
+                                ui.selectable_value(
+                                    &mut state.history_range,
+                                    range,
+                                    range.description(),
+                                );
+                            }
+                            let custom = matches!(state.history_range, HistoryRange::Custom(..));
+                            if ui.selectable_label(custom, "Custom").clicked() && !custom {
+                                let today = Local::now().date_naive();
+                                state.history_range =
+                                    HistoryRange::Custom(today - TimeDelta::days(6), today);
+                            }
+                        });
+                    if let HistoryRange::Custom(from, to) = &mut state.history_range {
+                        let [from_text, to_text] = &mut self.custom_range_text;
+                        date_field(ui, from_text, from);
+                        ui.label("–");
+                        date_field(ui, to_text, to);
+                    }
+                });
             });
         });
 
-        if resize_viewport && self.viewport_keyboard_type != state.keyboard_type {
-            let window_size = state.keyboard_type.window_size();
+        let window_size = window_size(&state.keyboard_type, state.show_finger_chart);
+        if resize_viewport && self.viewport_size != Some(window_size) {
             ctx.send_viewport_cmd(ViewportCommand::MinInnerSize(window_size));
             ctx.send_viewport_cmd(ViewportCommand::InnerSize(window_size));
-            self.viewport_keyboard_type = state.keyboard_type;
+            self.viewport_size = Some(window_size);
         }
 
         if state.show_log_window {
-            let preview_keycaps = typing_log_preview(&self.typing_log, state.keyboard_type);
+            let preview_keycaps = typing_log_preview(&self.typing_log, &state.keyboard_type);
             Window::new("Log Buffer")
                 .default_width(PREVIEW_VIEWPORT_WIDTH + 24.0)
                 .resizable(true)
@@ -207,6 +796,472 @@ impl eframe::App for KeyboardHeatmap {
                         });
                 });
         }
+
+        // the windows below count all time whatever range is shown
+        let history_range = state.history_range;
+        if state.show_ngram_window {
+            let layout = layout::layout_for(&state.keyboard_type);
+            let press_map = self.press_map.lock().unwrap();
+            let label = |keys: &[rdev::Key]| {
+                keys.iter()
+                    .map(|&key| keyboard::key_label(state.logical_layout, layout.as_deref(), key))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            };
+            let bigrams: Vec<_> = press_map
+                .ngrams
+                .top_bigrams(TOP_NGRAMS)
+                .into_iter()
+                .map(|(keys, count)| (label(&keys), count))
+                .collect();
+            let trigrams: Vec<_> = press_map
+                .ngrams
+                .top_trigrams(TOP_NGRAMS)
+                .into_iter()
+                .map(|(keys, count)| (label(&keys), count))
+                .collect();
+            Window::new("N-grams")
+                .resizable(true)
+                .open(&mut state.show_ngram_window)
+                .show(ctx, |ui| {
+                    all_time_note(ui, history_range);
+                    ui.label("Hover a key to outline the keys most often pressed after it.");
+                    ui.add_space(6.0);
+                    ui.horizontal_top(|ui| {
+                        ngram_table(ui, "Bigrams", &bigrams);
+                        ui.add_space(24.0);
+                        ngram_table(ui, "Trigrams", &trigrams);
+                    });
+                });
+        }
+
+        if state.show_chord_window {
+            let layout = layout::layout_for(&state.keyboard_type);
+            let chords: Vec<_> = self
+                .press_map
+                .lock()
+                .unwrap()
+                .chords
+                .top_chords(TOP_CHORDS)
+                .into_iter()
+                .map(|(modifiers, key, count)| {
+                    let chord = modifiers
+                        .iter()
+                        .map(|modifier| modifier.description().to_string())
+                        .chain([keyboard::key_label(
+                            state.logical_layout,
+                            layout.as_deref(),
+                            key,
+                        )])
+                        .collect::<Vec<_>>()
+                        .join("+");
+                    (chord, count)
+                })
+                .collect();
+            Window::new("Shortcuts")
+                .resizable(true)
+                .open(&mut state.show_chord_window)
+                .show(ctx, |ui| {
+                    all_time_note(ui, history_range);
+                    ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                        egui::Grid::new("chords").striped(true).show(ui, |ui| {
+                            for (rank, (chord, count)) in chords.iter().enumerate() {
+                                ui.label(format!("{}.", rank + 1));
+                                ui.monospace(chord);
+                                ui.label(count.to_string());
+                                ui.end_row();
+                            }
+                        });
+                    });
+                });
+        }
+
+        if state.show_metrics_window {
+            let metrics = layout::layout_for(&state.keyboard_type).map(|layout| {
+                let fingers =
+                    FingerMap::new(&layout, state.finger_overrides.get(&state.keyboard_type));
+                BigramMetrics::new(
+                    &layout,
+                    &fingers,
+                    self.press_map.lock().unwrap().ngrams.bigrams(),
+                )
+            });
+            Window::new("Finger Metrics")
+                .resizable(false)
+                .open(&mut state.show_metrics_window)
+                .show(ctx, |ui| match metrics {
+                    Some(metrics) => {
+                        ui.label("Share of all recorded bigrams, thumbs left out.");
+                        ui.add_space(6.0);
+                        metrics.draw_table(ui, "finger_metrics");
+                    }
+                    None => {
+                        ui.label("Keyboard layout could not be loaded");
+                    }
+                });
+        }
+
+        if state.show_speed_window {
+            let live_speed = self
+                .typing_log
+                .lock()
+                .unwrap()
+                .rolling_speed(SystemTime::now());
+            let today = Local::now().date_naive();
+            let days = self
+                .press_map
+                .lock()
+                .unwrap()
+                .daily_speeds(today - TimeDelta::days(speed::CHART_DAYS - 1), today);
+            Window::new("Typing Speed")
+                .resizable(false)
+                .open(&mut state.show_speed_window)
+                .show(ctx, |ui| {
+                    ui.label(speed_label(live_speed));
+                    ui.add_space(6.0);
+                    ui.strong("Words per minute, by day");
+                    speed::draw_daily_chart(ui, &days);
+                });
+        }
+
+        if let Some(kind) = due_break {
+            let break_left = state.breaks.break_left(&state.break_settings, now);
+            let presses_today = state.breaks.presses_today();
+            let skipped = egui::Modal::new(egui::Id::new("break_reminder"))
+                .show(ctx, |ui| {
+                    ui.set_max_width(320.0);
+                    ui.heading(kind.message());
+                    ui.add_space(6.0);
+                    match break_left {
+                        Some(left) => {
+                            ui.label(format!(
+                                "Keep off the keyboard for {}:{:02}",
+                                left.as_secs() / 60,
+                                left.as_secs() % 60
+                            ));
+                        }
+                        None => {
+                            ui.label(format!("{presses_today} presses today"));
+                        }
+                    }
+                    ui.add_space(6.0);
+                    ui.button("Skip").clicked()
+                })
+                .inner;
+            if skipped {
+                let State {
+                    break_settings,
+                    breaks,
+                    ..
+                } = &mut *state;
+                breaks.skip(break_settings, now);
+            } else if break_left.is_some() {
+                // the countdown runs while nothing is typed
+                ctx.request_repaint_after(Duration::from_secs(1));
+            }
+        }
+
+        if state.show_break_window {
+            let presses_today = state.breaks.presses_today();
+            let State {
+                show_break_window,
+                break_settings,
+                ..
+            } = &mut *state;
+            Window::new("Break Reminders")
+                .resizable(false)
+                .open(show_break_window)
+                .show(ctx, |ui| {
+                    ui.checkbox(&mut break_settings.enabled, "Remind me to take breaks");
+                    ui.add_enabled_ui(break_settings.enabled, |ui| {
+                        egui::Grid::new("break_settings").show(ui, |ui| {
+                            ui.label("Micro-break after");
+                            ui.add(
+                                egui::DragValue::new(&mut break_settings.micro_after_min)
+                                    .range(1..=120)
+                                    .suffix(" min"),
+                            );
+                            ui.label("of typing, lasting");
+                            ui.add(
+                                egui::DragValue::new(&mut break_settings.micro_length_s)
+                                    .range(5..=600)
+                                    .suffix(" s"),
+                            );
+                            ui.end_row();
+
+                            ui.label("Rest break after");
+                            ui.add(
+                                egui::DragValue::new(&mut break_settings.rest_after_min)
+                                    .range(1..=480)
+                                    .suffix(" min"),
+                            );
+                            ui.label("of typing, lasting");
+                            ui.add(
+                                egui::DragValue::new(&mut break_settings.rest_length_min)
+                                    .range(1..=60)
+                                    .suffix(" min"),
+                            );
+                            ui.end_row();
+
+                            ui.label("or after");
+                            ui.add(
+                                egui::DragValue::new(&mut break_settings.rest_after_presses)
+                                    .range(0..=100_000)
+                                    .speed(100)
+                                    .suffix(" presses"),
+                            )
+                            .on_hover_text("0 turns the press count off");
+                            ui.end_row();
+
+                            ui.label("Daily limit");
+                            ui.add(
+                                egui::DragValue::new(&mut break_settings.daily_limit)
+                                    .range(0..=1_000_000)
+                                    .speed(100)
+                                    .suffix(" presses"),
+                            )
+                            .on_hover_text("0 turns the limit off");
+                            ui.end_row();
+                        });
+                    });
+                    ui.label(format!("{presses_today} presses today"));
+                });
+        }
+
+        if state.show_activity_window {
+            let press_map = self.press_map.lock().unwrap();
+            let today = Local::now().date_naive();
+            let State {
+                hue,
+                show_activity_window,
+                timeline_days_back,
+                history_range,
+                ..
+            } = &mut *state;
+            Window::new("Activity")
+                .resizable(false)
+                .open(show_activity_window)
+                .show(ctx, |ui| {
+                    all_time_note(ui, *history_range);
+                    ui.strong(format!(
+                        "Presses by weekday and hour, {} since recording began",
+                        press_map.punch_card.total()
+                    ));
+                    press_map.punch_card.draw(ui, *hue);
+                    ui.add_space(6.0);
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        for days_back in (0..activity::TIMELINE_DAYS).rev() {
+                            let day = today - TimeDelta::days(days_back);
+                            let label = match days_back {
+                                0 => "Today".to_string(),
+                                _ => day.format("%a %m-%d").to_string(),
+                            };
+                            ui.selectable_value(timeline_days_back, days_back, label);
+                        }
+                    });
+                    let day = today - TimeDelta::days(*timeline_days_back);
+                    let hours = press_map.hourly_presses(day);
+                    ui.strong(format!(
+                        "Presses by hour on {}, {} in total",
+                        day.format("%Y-%m-%d"),
+                        hours.iter().sum::<u64>()
+                    ));
+                    activity::draw_hourly_chart(ui, &hours);
+                });
+        }
+
+        if state.show_simulation_window {
+            let press_map = self.press_map.lock().unwrap();
+            let layout = layout::layout_for(&state.keyboard_type);
+            let fingers = layout.as_deref().map(|layout| {
+                FingerMap::new(layout, state.finger_overrides.get(&state.keyboard_type))
+            });
+            let simulation = Simulation::new(state.logical_layout, &state.simulated_layout);
+            let typed = keyboard::Keyboard::new(&state.keyboard_type, state.hue)
+                .with_legends(state.logical_layout)
+                .with_repeats(state.count_repeats);
+            let typed_title = format!("Typed on {}", state.logical_layout.description());
+            let State {
+                keyboard_type,
+                hue,
+                count_repeats,
+                show_simulation_window,
+                simulated_layout,
+                ..
+            } = &mut *state;
+            Window::new("Layout Simulation")
+                .resizable(true)
+                .open(show_simulation_window)
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Replay on");
+                        egui::ComboBox::from_id_salt("simulated_layout")
+                            .selected_text(simulated_layout.description())
+                            .show_ui(ui, |ui| {
+                                for logical_layout in LogicalLayout::ALL {
+                                    ui.selectable_value(
+                                        simulated_layout,
+                                        SimulatedLayout::Logical(logical_layout),
+                                        logical_layout.description(),
+                                    );
+                                }
+                                let custom = matches!(simulated_layout, SimulatedLayout::Custom(_));
+                                if ui.selectable_label(custom, "Custom").clicked() && !custom {
+                                    *simulated_layout = SimulatedLayout::Custom(
+                                        simulate::QWERTY_MAPPING.to_string(),
+                                    );
+                                }
+                            });
+                        if let SimulatedLayout::Custom(mapping) = simulated_layout {
+                            ui.add(
+                                egui::TextEdit::singleline(mapping)
+                                    .desired_width(280.0)
+                                    .font(egui::TextStyle::Monospace),
+                            )
+                            .on_hover_text("Characters of the letter rows, from Q to /");
+                        }
+                    });
+                    ui.add_space(6.0);
+                    let simulation = match simulation {
+                        Ok(simulation) => simulation,
+                        Err(err) => {
+                            ui.label(format!("Invalid mapping: {err}"));
+                            return;
+                        }
+                    };
+                    let simulated_map = press_map.remapped(|key| simulation.key(key));
+                    let simulated = keyboard::Keyboard::new(keyboard_type, *hue)
+                        .with_key_legends(simulation.legends)
+                        .with_repeats(*count_repeats);
+                    let simulated_title = format!("Replayed on {}", simulated_layout.description());
+                    ScrollArea::horizontal().show(ui, |ui| {
+                        ui.horizontal_top(|ui| {
+                            simulation_side(
+                                ui,
+                                &typed_title,
+                                typed,
+                                &press_map,
+                                layout.as_deref().zip(fingers.as_ref()),
+                            );
+                            ui.add_space(mouse::PANEL_GAP);
+                            simulation_side(
+                                ui,
+                                &simulated_title,
+                                simulated,
+                                &simulated_map,
+                                layout.as_deref().zip(fingers.as_ref()),
+                            );
+                        });
+                    });
+                });
+        }
+
+        let mut report_open = self.import_report.is_some();
+        if let Some(report) = &self.import_report {
+            Window::new("KLE Import")
+                .resizable(true)
+                .open(&mut report_open)
+                .show(ctx, |ui| match report {
+                    ImportReport::Imported { name, unbound } if unbound.is_empty() => {
+                        ui.label(format!("Imported \"{name}\", all keys are bound."));
+                    }
+                    ImportReport::Imported { name, unbound } => {
+                        ui.label(format!(
+                            "Imported \"{name}\", these keys could not be bound \
+                             or fully drawn:"
+                        ));
+                        ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                            for key in unbound {
+                                ui.label(key);
+                            }
+                        });
+                    }
+                    ImportReport::Failed(err) => {
+                        ui.label(format!("Import failed: {err}"));
+                    }
+                });
+        }
+        if !report_open {
+            self.import_report = None;
+        }
+
+        if let Some(preview) = &self.snapshot_preview {
+            let mut preview_open = true;
+            let press_map = self.press_map.lock().unwrap();
+            let keyboard = || {
+                keyboard::Keyboard::new(&state.keyboard_type, state.hue)
+                    .with_legends(state.logical_layout)
+                    .with_mode(state.heatmap_mode)
+                    .with_repeats(state.count_repeats)
+            };
+            Window::new("Snapshot Preview")
+                .resizable(true)
+                .open(&mut preview_open)
+                .show(ctx, |ui| {
+                    ScrollArea::horizontal().show(ui, |ui| {
+                        ui.horizontal_top(|ui| {
+                            let now = format!("Now, {} presses", press_map.total_presses());
+                            simulation_side(ui, &now, keyboard(), &press_map, None);
+                            ui.add_space(20.0);
+                            let then = format!(
+                                "{}, {} presses",
+                                preview.title,
+                                preview.press_map.total_presses()
+                            );
+                            simulation_side(ui, &then, keyboard(), &preview.press_map, None);
+                        });
+                    });
+                });
+            if !preview_open {
+                self.snapshot_preview = None;
+            }
+        }
+
+        if let Some(err) = &self.clear_failed {
+            let modal = egui::Modal::new(egui::Id::new("clear_failed")).show(ctx, |ui| {
+                ui.set_max_width(360.0);
+                ui.heading("Clear data without a snapshot?");
+                ui.add_space(6.0);
+                ui.colored_label(
+                    Color32::from_rgb(200, 40, 40),
+                    format!("The data could not be snapshotted first: {err}"),
+                );
+                ui.label("Clearing it anyway cannot be undone.");
+                ui.add_space(6.0);
+                ui.horizontal(|ui| {
+                    let clear = ui.button("Clear anyway").clicked();
+                    let keep = ui.button("Keep data").clicked();
+                    (clear, keep)
+                })
+                .inner
+            });
+            let (clear, keep) = modal.inner;
+            if clear {
+                clear_counts(
+                    &mut state,
+                    &mut self.press_map.lock().unwrap(),
+                    &mut self.typing_log.lock().unwrap(),
+                );
+            }
+            if clear || keep || modal.should_close() {
+                self.clear_failed = None;
+            }
+        }
+
+        let mut warning_open = self.load_warning.is_some();
+        if let Some(warning) = &self.load_warning {
+            Window::new("Saved Data Not Loaded")
+                .resizable(false)
+                .open(&mut warning_open)
+                .show(ctx, |ui| {
+                    ui.set_max_width(360.0);
+                    ui.colored_label(Color32::from_rgb(200, 40, 40), warning);
+                });
+        }
+        if !warning_open {
+            self.load_warning = None;
+        }
     }
 
     fn raw_input_hook(&mut self, _ctx: &egui::Context, raw_input: &mut egui::RawInput) {
@@ -231,6 +1286,9 @@ impl eframe::App for KeyboardHeatmap {
         if let Err(err) = self.save_to_disk() {
             eprintln!("failed to save state: {err}");
         }
+        if let Err(err) = EventLog::flush(&self.events) {
+            eprintln!("failed to write events: {err}");
+        }
     }
 }
 
@@ -239,19 +1297,38 @@ impl KeyboardHeatmap {
         state: Arc<Mutex<State>>,
         press_map: Arc<Mutex<PressTimesMap>>,
         typing_log: Arc<Mutex<TypingLog>>,
+        events: Arc<Mutex<EventLog>>,
         tray_controller: Option<TrayController>,
+        load_warning: Option<String>,
     ) -> Self {
-        let viewport_keyboard_type = state.lock().unwrap().keyboard_type;
-        Self {
-            viewport_keyboard_type,
+        let viewport_size = {
+            let state = state.lock().unwrap();
+            Some(window_size(&state.keyboard_type, state.show_finger_chart))
+        };
+        let app = Self {
+            viewport_size,
             state,
             press_map,
             typing_log,
+            events,
             tray_controller,
             pending_screenshot_path: None,
             window_visible: true,
             allow_root_close: false,
-        }
+            import_report: None,
+            load_warning,
+            clear_failed: None,
+            custom_range_text: Default::default(),
+            alerted_break: None,
+            snapshot_presses: HashMap::new(),
+            snapshot_preview: None,
+            range_queries: RangeQueries::new(),
+            pending_profile: None,
+            new_profile_name: String::new(),
+            new_profile_error: None,
+        };
+        app.update_tray_profiles();
+        app
     }
 
     fn poll_tray_commands(&self) -> Vec<TrayCommand> {
@@ -267,62 +1344,626 @@ impl KeyboardHeatmap {
     }
 
     fn save_to_disk(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let state = self.state.lock().unwrap();
-        let press_map = self.press_map.lock().unwrap();
-        let persisted = PersistedState {
-            keyboard_type: state.keyboard_type,
-            hue: state.hue,
-            start_time: state.start_time,
-            show_log_window: state.show_log_window,
-            press_entries: press_map.persisted_entries(),
+        save_state(&self.state, &self.press_map)
+    }
+
+    /// Saves the current profile and loads `profile` in its place, returns
+    /// whether it switched
+    fn switch_profile(&mut self, profile: &str) -> bool {
+        let switched = self.load_profile(profile);
+        // the tray checks the item clicked, it is unchecked again if the
+        // switch failed
+        self.update_tray_profiles();
+        switched
+    }
+
+    fn load_profile(&mut self, profile: &str) -> bool {
+        let _saving = SAVE_LOCK.lock().unwrap();
+        let mut state = self.state.lock().unwrap();
+        if state.profile == profile {
+            return false;
+        }
+        // nothing is recorded between the save and the switch
+        let mut press_map = self.press_map.lock().unwrap();
+        let mut typing_log = self.typing_log.lock().unwrap();
+        // a state file from a newer version is left as it is
+        if !state.read_only {
+            if let Err(err) = write_state_file(&state.profile, &persisted_state(&state, &press_map))
+            {
+                self.load_warning = Some(format!(
+                    "The profile {} could not be saved and stays in use: {err}",
+                    state.profile
+                ));
+                return false;
+            }
+        }
+        // the worker waits for the state, so no events are pushed meanwhile
+        if let Err(err) = EventLog::flush(&self.events) {
+            eprintln!("failed to write events: {err}");
+        }
+
+        let (loaded_state, loaded_press_map, load_warning) = load_state(profile);
+        *self.events.lock().unwrap() = EventLog::open(
+            loaded_state.event_store,
+            &profile_dir(profile),
+            &loaded_press_map.persisted_history(),
+        )
+        .unwrap_or_else(|err| {
+            eprintln!("failed to open the event store: {err}");
+            EventLog::default()
+        });
+        *press_map = loaded_press_map;
+        typing_log.clear();
+        // the breaks follow the hands, not the keyboard
+        let breaks = std::mem::take(&mut state.breaks);
+        *state = State {
+            breaks,
+            break_settings: state.break_settings.clone(),
+            recording_enabled: state.recording_enabled,
+            ..loaded_state
+        };
+        if let Err(err) = profile::set_active_profile(&app_data_dir(), profile) {
+            eprintln!("failed to remember the active profile: {err}");
+        }
+        self.load_warning = load_warning;
+        self.range_queries.reset();
+        self.snapshot_preview = None;
+        true
+    }
+
+    fn update_tray_profiles(&self) {
+        let Some(tray_controller) = &self.tray_controller else {
+            return;
+        };
+        let active = self.state.lock().unwrap().profile.clone();
+        let profiles = profile::list_profiles(&app_data_dir());
+        if let Err(err) = tray_controller.set_profiles(&profiles, &active) {
+            eprintln!("failed to update tray profiles: {err}");
+        }
+    }
+}
+
+// keeps the autosave thread and the save on exit from writing at once
+static SAVE_LOCK: Mutex<()> = Mutex::new(());
+
+fn save_state(
+    state: &Mutex<State>,
+    press_map: &Mutex<PressTimesMap>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let _saving = SAVE_LOCK.lock().unwrap();
+    // the counts are copied out so recording is not blocked by the write
+    let (profile, persisted) = {
+        let state = state.lock().unwrap();
+        if state.read_only {
+            return Ok(());
+        }
+        let press_map = press_map.lock().unwrap();
+        (state.profile.clone(), persisted_state(&state, &press_map))
+    };
+    let saved = write_state_file(&profile, &persisted)?;
+    state.lock().unwrap().last_saved = Some(saved);
+    Ok(())
+}
+
+/// Writes the state file of `profile` and its first backup of the day,
+/// returns when it was written
+fn write_state_file(
+    profile: &str,
+    persisted: &PersistedState,
+) -> Result<DateTime<chrono::Local>, Box<dyn std::error::Error + Send + Sync>> {
+    let bytes = serde_json::to_vec_pretty(persisted)?;
+    let path = state_file_path(profile);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    storage::write_atomically(&path, &bytes)?;
+    let now = Local::now();
+
+    // the state is saved even if the backup fails, it is tried again next save
+    let backups = backup_dir(profile);
+    if !storage::has_daily_backup(&backups, now.date_naive()) {
+        if let Err(err) =
+            storage::write_snapshot(&backups, SnapshotKind::Daily, now.naive_local(), &bytes)
+        {
+            eprintln!("failed to write the daily backup: {err}");
+        }
+    }
+    Ok(now)
+}
+
+fn persisted_state(state: &State, press_map: &PressTimesMap) -> PersistedState {
+    PersistedState {
+        version: storage::STATE_VERSION,
+        keyboard_type: state.keyboard_type.clone(),
+        logical_layout: state.logical_layout,
+        heatmap_mode: state.heatmap_mode,
+        count_repeats: state.count_repeats,
+        hue: state.hue,
+        start_time: state.start_time,
+        show_log_window: state.show_log_window,
+        show_ngram_window: state.show_ngram_window,
+        show_chord_window: state.show_chord_window,
+        show_metrics_window: state.show_metrics_window,
+        show_simulation_window: state.show_simulation_window,
+        simulated_layout: state.simulated_layout.clone(),
+        show_speed_window: state.show_speed_window,
+        show_activity_window: state.show_activity_window,
+        show_break_window: state.show_break_window,
+        break_settings: state.break_settings.clone(),
+        show_finger_chart: state.show_finger_chart,
+        show_finger_tint: state.show_finger_tint,
+        finger_overrides: state
+            .finger_overrides
+            .iter()
+            .filter(|(_, overrides)| !overrides.is_empty())
+            .map(|(keyboard_type, overrides)| {
+                (
+                    keyboard_type.clone(),
+                    finger::persisted_overrides(overrides),
+                )
+            })
+            .collect(),
+        ngram_idle_gap_ms: state.ngram_idle_gap_ms,
+        autosave_interval_min: state.autosave_interval_min,
+        autosave_presses: state.autosave_presses,
+        event_store: state.event_store,
+        press_entries: press_map.persisted_entries(),
+        char_entries: press_map.persisted_char_entries(),
+        repeat_entries: press_map.persisted_repeat_entries(),
+        hold_entries: press_map.persisted_hold_entries(),
+        mouse_entries: press_map.persisted_mouse_entries(),
+        error_entries: press_map.persisted_error_entries(),
+        history: press_map.persisted_history(),
+        ngram_entries: press_map.ngrams.persisted_entries(),
+        chords: press_map.chords.persisted_entries(),
+        punch_card: press_map.punch_card.clone(),
+    }
+}
+
+/// Writes the current state to the backup directory
+fn take_snapshot(
+    state: &State,
+    press_map: &PressTimesMap,
+    kind: SnapshotKind,
+) -> Result<PathBuf, Box<dyn std::error::Error + Send + Sync>> {
+    let bytes = serde_json::to_vec_pretty(&persisted_state(state, press_map))?;
+    Ok(storage::write_snapshot(
+        &backup_dir(&state.profile),
+        kind,
+        Local::now().naive_local(),
+        &bytes,
+    )?)
+}
+
+/// Clears the counts once a snapshot of them is written, so clearing can be
+/// undone from the snapshots menu. Nothing is cleared if the snapshot fails
+fn clear_data(
+    state: &mut State,
+    press_map: &mut PressTimesMap,
+    typing_log: &mut TypingLog,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    take_snapshot(state, press_map, SnapshotKind::BeforeClear)?;
+    clear_counts(state, press_map, typing_log);
+    Ok(())
+}
+
+// clearing without a snapshot cannot be undone
+fn clear_counts(state: &mut State, press_map: &mut PressTimesMap, typing_log: &mut TypingLog) {
+    state.start_time = chrono::Local::now();
+    press_map.clear();
+    typing_log.clear();
+}
+
+/// Replaces the state and counts with a snapshot, the current ones are
+/// snapshotted first
+fn restore_snapshot(
+    state: &mut State,
+    press_map: &mut PressTimesMap,
+    path: &Path,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let saved = read_state_file(path)?.ok_or(SNAPSHOT_GONE)?;
+    take_snapshot(state, press_map, SnapshotKind::BeforeRestore)?;
+    let (restored_state, restored_press_map) = state_from_persisted(saved, &state.profile);
+    *press_map = restored_press_map;
+    // what the app follows since it started stays as it is
+    let breaks = std::mem::take(&mut state.breaks);
+    *state = State {
+        profile: std::mem::take(&mut state.profile),
+        breaks,
+        break_settings: state.break_settings.clone(),
+        last_saved: state.last_saved,
+        event_store: state.event_store,
+        recording_enabled: state.recording_enabled,
+        read_only: state.read_only,
+        ..restored_state
+    };
+    Ok(())
+}
+
+// a shown range is counted from the event store again after this long
+const RANGE_REQUERY: Duration = Duration::from_secs(60);
+
+impl RangeQueries {
+    fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            last: None,
+            running: None,
+            sender,
+            receiver,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.last = None;
+        self.running = None;
+    }
+
+    /// Counts of `request` once queried, the last ones stay shown while they
+    /// are queried again
+    fn press_map(&self, request: &RangeRequest) -> Option<&PressTimesMap> {
+        self.last
+            .as_ref()
+            .filter(|query| query.request == *request)?
+            .press_map
+            .as_ref()
+    }
+
+    /// Takes the finished queries and queries `request` when it changed or
+    /// its counts are stale. The store keeps no typed text, so the characters
+    /// come from the history buckets of `live`
+    fn refresh(
+        &mut self,
+        ctx: &egui::Context,
+        request: Option<RangeRequest>,
+        events: &Arc<Mutex<EventLog>>,
+        live: &Mutex<PressTimesMap>,
+    ) {
+        for mut query in self.receiver.try_iter() {
+            if self.running.as_ref() != Some(&query.request) {
+                continue;
+            }
+            self.running = None;
+            if let Some(press_map) = &mut query.press_map {
+                let (from, to) = query.request.bounds;
+                press_map.chars = live.lock().unwrap().chars_in_range(from, to);
+            }
+            self.last = Some(query);
+        }
+        let Some(request) = request else {
+            self.reset();
+            return;
         };
+        let fresh = self.last.as_ref().is_some_and(|query| {
+            query.request == request && query.queried.elapsed() < RANGE_REQUERY
+        });
+        if fresh || self.running.as_ref() == Some(&request) {
+            return;
+        }
+        self.running = Some(request.clone());
+        let events = events.clone();
+        let sender = self.sender.clone();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            // the log is only locked to take its pending events
+            let reader = EventLog::reader(&events);
+            let (from, to) = request.bounds;
+            let queried = reader.and_then(|store| match store {
+                Some(store) => {
+                    event_store::press_map_between(store.as_ref(), from, to, request.idle_gap)
+                        .map(Some)
+                }
+                None => Ok(None),
+            });
+            let press_map = queried.unwrap_or_else(|err| {
+                eprintln!("failed to query the event store: {err}");
+                None
+            });
+            let query = RangeQuery {
+                request,
+                queried: Instant::now(),
+                press_map,
+            };
+            // the app may be closing
+            if sender.send(query).is_ok() {
+                ctx.request_repaint();
+            }
+        });
+    }
+}
+
+/// Records into the store of `kind` from now on, a new store imports the
+/// history counted so far
+fn switch_event_store(
+    events: &Mutex<EventLog>,
+    kind: EventStoreKind,
+    profile: &str,
+    press_map: &PressTimesMap,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    // the pending events are part of the history the new store imports
+    if let Err(err) = EventLog::flush(events) {
+        eprintln!("failed to write events: {err}");
+    }
+    let opened = EventLog::open(kind, &profile_dir(profile), &press_map.persisted_history())?;
+    *events.lock().unwrap() = opened;
+    Ok(())
+}
+
+// space between the keyboard and the finger load chart
+const FINGER_CHART_SPACING: f32 = 20.;
+
+// rows of each table in the n-gram window
+const TOP_NGRAMS: usize = 20;
+// rows of the shortcut window
+const TOP_CHORDS: usize = 50;
+
+fn ngram_table(ui: &mut egui::Ui, title: &str, rows: &[(String, u32)]) {
+    ui.vertical(|ui| {
+        ui.strong(title);
+        egui::Grid::new(title).striped(true).show(ui, |ui| {
+            for (keys, count) in rows {
+                ui.monospace(keys);
+                ui.label(count.to_string());
+                ui.end_row();
+            }
+        });
+    });
+}
+
+fn all_time_note(ui: &mut egui::Ui, range: HistoryRange) {
+    if range != HistoryRange::AllTime {
+        ui.weak(format!(
+            "Counted over all time, not {}",
+            range.description().to_lowercase()
+        ));
+    }
+}
+
+fn speed_label(speed: Speed) -> String {
+    if !speed.is_measurable() {
+        return "Last minute: not enough typing".to_string();
+    }
+    format!(
+        "Last minute: {:.0} WPM, {:.0} CPM",
+        speed.wpm(),
+        speed.cpm()
+    )
+}
+
+/// One keyboard of the layout simulation with its finger loads and metrics
+fn simulation_side(
+    ui: &mut egui::Ui,
+    title: &str,
+    mut keyboard: keyboard::Keyboard,
+    map: &PressTimesMap,
+    fingers: Option<(&layout::KeyboardLayout, &FingerMap)>,
+) {
+    ui.push_id(title, |ui| {
+        ui.vertical(|ui| {
+            ui.strong(title);
+            keyboard.draw(map, ui);
+            if let Some((layout, fingers)) = fingers {
+                ui.add_space(FINGER_CHART_SPACING);
+                fingers.loads(map).draw_chart(ui);
+                ui.add_space(6.0);
+                BigramMetrics::new(layout, fingers, map.ngrams.bigrams()).draw_table(ui, title);
+            }
+        });
+    });
+}
 
-        let path = state_file_path();
-        if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent)?;
+/// Text field editing a date, the date only changes once the text parses
+fn date_field(ui: &mut egui::Ui, text: &mut String, date: &mut NaiveDate) {
+    let response = ui.add(egui::TextEdit::singleline(text).desired_width(80.0));
+    if response.changed() {
+        if let Ok(parsed) = text.parse() {
+            *date = parsed;
         }
-        fs::write(path, serde_json::to_vec_pretty(&persisted)?)?;
-        Ok(())
+    } else if !response.has_focus() {
+        *text = date.to_string();
     }
 }
 
-pub fn initial_keyboard_type() -> KeyboardType {
-    load_persisted_state()
-        .map(|saved| saved.keyboard_type)
-        .unwrap_or_else(default_keyboard_type_for_current_os)
+/// Asks for a KLE JSON export and stores it as a user layout named after the file
+fn import_kle_layout(
+) -> Result<Option<(KeyboardType, ImportReport)>, Box<dyn std::error::Error + Send + Sync>> {
+    let path = native_dialog::DialogBuilder::file()
+        .add_filter("KLE JSON", ["json"])
+        .open_single_file()
+        .show()?;
+    let Some(path) = path else {
+        return Ok(None);
+    };
+
+    let file_name = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("kle-layout")
+        .to_string();
+    let import = kle::parse(&fs::read_to_string(&path)?, &file_name)?;
+    layout::save_user_layout(&file_name, &import.layout)?;
+
+    Ok(Some((
+        KeyboardType::Custom(file_name),
+        ImportReport::Imported {
+            name: import.layout.name,
+            unbound: import.unbound,
+        },
+    )))
 }
 
-fn default_state() -> State {
+pub fn initial_window_size() -> Vec2 {
+    let profile = profile::active_profile(&app_data_dir());
+    match read_persisted_state(&profile).ok().flatten() {
+        Some(saved) => window_size(&saved.keyboard_type, saved.show_finger_chart),
+        None => window_size(&default_keyboard_type_for_current_os(), false),
+    }
+}
+
+fn window_size(keyboard_type: &KeyboardType, show_finger_chart: bool) -> Vec2 {
+    let chart = if show_finger_chart {
+        FINGER_CHART_SPACING + finger::CHART_HEIGHT
+    } else {
+        0.
+    };
+    keyboard_type.window_size() + vec2(0., chart)
+}
+
+fn default_state(profile: &str) -> State {
     State {
+        profile: profile.to_string(),
         keyboard_type: default_keyboard_type_for_current_os(),
+        logical_layout: LogicalLayout::default(),
+        heatmap_mode: HeatmapMode::default(),
+        count_repeats: false,
+        history_range: HistoryRange::default(),
         hue: 220. / 360.,
         start_time: chrono::Local::now(),
         show_log_window: false,
+        show_ngram_window: false,
+        show_chord_window: false,
+        show_metrics_window: false,
+        show_simulation_window: false,
+        simulated_layout: SimulatedLayout::default(),
+        show_speed_window: false,
+        show_activity_window: false,
+        timeline_days_back: 0,
+        show_break_window: false,
+        break_settings: BreakSettings::default(),
+        breaks: BreakTracker::default(),
+        show_finger_chart: false,
+        show_finger_tint: false,
+        assign_finger: None,
+        finger_overrides: HashMap::new(),
+        ngram_idle_gap_ms: ngram::DEFAULT_IDLE_GAP_MS,
+        autosave_interval_min: storage::DEFAULT_AUTOSAVE_INTERVAL_MIN,
+        autosave_presses: storage::DEFAULT_AUTOSAVE_PRESSES,
+        last_saved: None,
+        event_store: EventStoreKind::default(),
         recording_enabled: true,
+        read_only: false,
     }
 }
 
-fn load_state() -> (State, PressTimesMap) {
-    let Some(saved) = load_persisted_state() else {
-        return (default_state(), PressTimesMap::new());
+/// Saved state and counts of `profile`, with a warning when its state file
+/// was unreadable or from a newer version
+fn load_state(profile: &str) -> (State, PressTimesMap, Option<String>) {
+    let path = state_file_path(profile);
+    let (state, warning) = match read_state_file(&path) {
+        Ok(Some(saved)) => {
+            let (state, press_map) = state_from_persisted(saved, profile);
+            return (state, press_map, None);
+        }
+        Ok(None) => return (default_state(profile), PressTimesMap::new(), None),
+        // saving over it would lose what the newer version recorded, so
+        // neither the state nor the events are written
+        Err(problem @ ReadError::NewerVersion(_)) => {
+            let state = State {
+                event_store: EventStoreKind::Off,
+                read_only: true,
+                ..default_state(profile)
+            };
+            let warning = format!(
+                "The saved data in {} {problem}. It is left as it is and nothing is saved \
+                 until the app is updated.",
+                path.display()
+            );
+            (state, warning)
+        }
+        Err(problem) => (default_state(profile), quarantine(&path, problem)),
     };
+    eprintln!("{warning}");
+    (state, PressTimesMap::new(), Some(warning))
+}
+
+fn state_from_persisted(saved: PersistedState, profile: &str) -> (State, PressTimesMap) {
+    let mut press_map = PressTimesMap::from_persisted_entries(saved.press_entries);
+    press_map.chars = saved.char_entries.into_iter().collect();
+    press_map.restore_repeat_entries(saved.repeat_entries);
+    press_map.restore_hold_entries(saved.hold_entries);
+    press_map.restore_mouse_entries(saved.mouse_entries);
+    press_map.restore_error_entries(saved.error_entries);
+    press_map.restore_history(saved.history, saved.start_time.naive_local());
+    press_map.ngrams.restore_entries(saved.ngram_entries);
+    press_map.chords.restore_entries(saved.chords);
+    press_map.punch_card = saved.punch_card;
+    press_map.ngrams.idle_gap = Duration::from_millis(saved.ngram_idle_gap_ms);
 
     (
         State {
+            profile: profile.to_string(),
             keyboard_type: saved.keyboard_type,
+            logical_layout: saved.logical_layout,
+            heatmap_mode: saved.heatmap_mode,
+            count_repeats: saved.count_repeats,
+            history_range: HistoryRange::default(),
             hue: saved.hue,
             start_time: saved.start_time,
             show_log_window: saved.show_log_window,
+            show_ngram_window: saved.show_ngram_window,
+            show_chord_window: saved.show_chord_window,
+            show_metrics_window: saved.show_metrics_window,
+            show_simulation_window: saved.show_simulation_window,
+            simulated_layout: saved.simulated_layout,
+            show_speed_window: saved.show_speed_window,
+            show_activity_window: saved.show_activity_window,
+            timeline_days_back: 0,
+            show_break_window: saved.show_break_window,
+            break_settings: saved.break_settings,
+            breaks: BreakTracker::default(),
+            show_finger_chart: saved.show_finger_chart,
+            show_finger_tint: saved.show_finger_tint,
+            assign_finger: None,
+            finger_overrides: saved
+                .finger_overrides
+                .into_iter()
+                .map(|(keyboard_type, entries)| (keyboard_type, finger::restore_overrides(entries)))
+                .collect(),
+            ngram_idle_gap_ms: saved.ngram_idle_gap_ms,
+            autosave_interval_min: saved.autosave_interval_min,
+            autosave_presses: saved.autosave_presses,
+            last_saved: None,
+            event_store: saved.event_store,
             recording_enabled: true,
+            read_only: false,
         },
-        PressTimesMap::from_persisted_entries(saved.press_entries),
+        press_map,
     )
 }
 
-fn load_persisted_state() -> Option<PersistedState> {
-    let path = state_file_path();
-    let bytes = fs::read(path).ok()?;
-    serde_json::from_slice::<PersistedState>(&bytes).ok()
+fn read_persisted_state(profile: &str) -> Result<Option<PersistedState>, ReadError> {
+    read_state_file(&state_file_path(profile))
+}
+
+const SNAPSHOT_GONE: &str = "the snapshot no longer exists";
+
+fn snapshot_presses(path: &Path) -> Result<u64, String> {
+    let saved = read_state_file(path)?.ok_or(SNAPSHOT_GONE)?;
+    Ok(saved
+        .press_entries
+        .iter()
+        .map(|(_, count)| u64::from(*count))
+        .sum())
+}
+
+fn read_state_file(path: &Path) -> Result<Option<PersistedState>, ReadError> {
+    storage::read_versioned(path)?
+        .map(serde_json::from_value::<PersistedState>)
+        .transpose()
+        .map_err(|err| ReadError::Unreadable(format!("does not hold the expected fields: {err}")))
+}
+
+/// Moves an unreadable state file aside so the next save does not overwrite
+/// it, returns what happened for the UI
+fn quarantine(path: &Path, problem: ReadError) -> String {
+    let moved = match storage::quarantine(path) {
+        Ok(target) => format!("It was moved to {}.", target.display()),
+        Err(err) => format!("It could not be moved aside: {err}."),
+    };
+    format!(
+        "The saved data in {} {problem}. {moved} Counting starts over from now.",
+        path.display()
+    )
 }
 
 fn default_keyboard_type_for_current_os() -> KeyboardType {
@@ -332,11 +1973,19 @@ fn default_keyboard_type_for_current_os() -> KeyboardType {
     }
 }
 
-fn state_file_path() -> PathBuf {
-    app_data_dir().join(STATE_FILE)
+fn profile_dir(profile: &str) -> PathBuf {
+    profile::profile_dir(&app_data_dir(), profile)
 }
 
-fn app_data_dir() -> PathBuf {
+fn state_file_path(profile: &str) -> PathBuf {
+    profile_dir(profile).join(STATE_FILE)
+}
+
+fn backup_dir(profile: &str) -> PathBuf {
+    profile_dir(profile).join(BACKUP_DIR)
+}
+
+pub fn app_data_dir() -> PathBuf {
     #[cfg(target_os = "windows")]
     {
         std::env::var_os("APPDATA")
@@ -377,7 +2026,7 @@ const PREVIEW_VISIBLE_ROWS: usize = 3;
 
 fn typing_log_preview(
     typing_log: &Arc<Mutex<TypingLog>>,
-    keyboard_type: KeyboardType,
+    keyboard_type: &KeyboardType,
 ) -> Vec<keyboard::KeyPreviewSpec> {
     typing_log.lock().unwrap().preview_keycaps(keyboard_type)
 }
@@ -498,12 +2147,35 @@ pub fn setup_ui(
     cc.egui_ctx.set_theme(Theme::Light);
     TrayController::install_repaint_forwarder(&cc.egui_ctx);
 
-    let (sender, receiver) = mpsc::sync_channel(1);
-    let (saved_state, saved_press_map) = load_state();
+    let (sender, receiver) = mpsc::channel();
+    let profile = profile::active_profile(&app_data_dir());
+    let (mut saved_state, saved_press_map, load_warning) = load_state(&profile);
+    // the daily keystroke limit counts what was typed today before a restart
+    let presses_today: u64 = saved_press_map
+        .hourly_presses(Local::now().date_naive())
+        .iter()
+        .sum();
+    saved_state
+        .breaks
+        .seed_today(presses_today as u32, SystemTime::now());
+
+    // a new event store starts with the history counted so far
+    let events = match EventLog::open(
+        saved_state.event_store,
+        &profile_dir(&profile),
+        &saved_press_map.persisted_history(),
+    ) {
+        Ok(events) => events,
+        Err(err) => {
+            eprintln!("failed to open the event store: {err}");
+            EventLog::default()
+        }
+    };
 
     let state = Arc::new(Mutex::new(saved_state));
     let press_map = Arc::new(Mutex::new(saved_press_map));
     let typing_log = Arc::new(Mutex::new(TypingLog::new()));
+    let events = Arc::new(Mutex::new(events));
 
     {
         thread::spawn(move || {
@@ -514,20 +2186,92 @@ pub fn setup_ui(
     {
         let press_map = press_map.clone();
         let typing_log = typing_log.clone();
+        let events = events.clone();
         let state = state.clone();
         let egui_ctx = cc.egui_ctx.clone();
         thread::spawn(move || loop {
-            if let Ok(event) = receiver.recv() {
-                if !state.lock().unwrap().recording_enabled {
+            if let Ok(InputEvent { event, modifiers }) = receiver.recv() {
+                let recorded = RecordedEvent::from_event(&event, modifiers);
+                if recorded.is_empty() {
+                    continue;
+                }
+                {
+                    // the state is locked before the press map, as the UI does
+                    let mut state = state.lock().unwrap();
+                    if !state.recording_enabled {
+                        continue;
+                    }
+                    let mut press_map = press_map.lock().unwrap();
+                    let mut typing_log = typing_log.lock().unwrap();
+                    // the typed text is counted live and never stored
+                    let text = event.name.as_deref().filter(|name| is_text(name));
+                    for recorded_event in &recorded {
+                        let auto_repeat = recorded_event.apply_typed(&mut press_map, text);
+                        if let rdev::EventType::KeyPress(_) = event.event_type {
+                            if !auto_repeat {
+                                let State {
+                                    break_settings,
+                                    breaks,
+                                    ..
+                                } = &mut *state;
+                                breaks.key_press(break_settings, event.time);
+                            }
+                            typing_log.push_event(&event, auto_repeat);
+                        }
+                    }
+                }
+                // pushed with nothing else locked, a flush only holds the log
+                // to take the pending events
+                events.lock().unwrap().push(recorded);
+                egui_ctx.request_repaint();
+            }
+        });
+    }
+
+    {
+        let press_map = press_map.clone();
+        let events = events.clone();
+        let state = state.clone();
+        thread::spawn(move || {
+            let total_presses = |press_map: &Mutex<PressTimesMap>| {
+                let press_map = press_map.lock().unwrap();
+                press_map.total_presses() + press_map.total_repeats()
+            };
+            let mut saved_at = Instant::now();
+            let mut saved_presses = total_presses(&press_map);
+            let mut events_failed = false;
+            loop {
+                thread::sleep(storage::AUTOSAVE_POLL);
+                // events are written every poll, a failure is reported once
+                match EventLog::flush(&events) {
+                    Ok(()) => events_failed = false,
+                    Err(err) if !events_failed => {
+                        eprintln!("failed to write events: {err}");
+                        events_failed = true;
+                    }
+                    Err(_) => {}
+                }
+                let (interval, presses_due) = {
+                    let state = state.lock().unwrap();
+                    (
+                        Duration::from_secs(state.autosave_interval_min * 60),
+                        state.autosave_presses,
+                    )
+                };
+                let presses = total_presses(&press_map);
+                // clearing the data restarts the count
+                let typed = presses.saturating_sub(saved_presses);
+                if saved_at.elapsed() < interval && typed < presses_due {
+                    if presses < saved_presses {
+                        saved_presses = presses;
+                    }
                     continue;
                 }
-                let mut press_map = press_map.lock().unwrap();
-                let mut typing_log = typing_log.lock().unwrap();
-                if let rdev::EventType::KeyPress(key) = event.event_type {
-                    press_map.key_press(key);
-                    typing_log.push_event(&event);
-                    egui_ctx.request_repaint();
+                if let Err(err) = save_state(&state, &press_map) {
+                    eprintln!("failed to autosave state: {err}");
                 }
+                saved_at = Instant::now();
+                saved_presses = presses;
             }
         });
     }
@@ -537,6 +2281,8 @@ pub fn setup_ui(
         state,
         press_map,
         typing_log,
+        events,
         tray_controller,
+        load_warning,
     )))
 }
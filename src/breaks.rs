@@ -0,0 +1,324 @@
+use std::time::{Duration, SystemTime};
+
+use chrono::{DateTime, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+/// When break reminders are raised, set in the break reminders window
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BreakSettings {
+    pub enabled: bool,
+    /// minutes of typing without a micro-break before one is due
+    pub micro_after_min: u32,
+    /// seconds without a press that count as a micro-break
+    pub micro_length_s: u32,
+    /// minutes of typing without a rest break before one is due
+    pub rest_after_min: u32,
+    /// presses since the last rest break before one is due, 0 turns it off
+    pub rest_after_presses: u32,
+    /// minutes without a press that count as a rest break
+    pub rest_length_min: u32,
+    /// presses in a day before the limit is reported, 0 turns it off
+    pub daily_limit: u32,
+}
+
+impl Default for BreakSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            micro_after_min: 10,
+            micro_length_s: 30,
+            rest_after_min: 50,
+            rest_after_presses: 10_000,
+            rest_length_min: 5,
+            daily_limit: 0,
+        }
+    }
+}
+
+impl BreakSettings {
+    fn micro_after(&self) -> Duration {
+        Duration::from_secs(u64::from(self.micro_after_min) * 60)
+    }
+
+    fn micro_length(&self) -> Duration {
+        Duration::from_secs(u64::from(self.micro_length_s))
+    }
+
+    fn rest_after(&self) -> Duration {
+        Duration::from_secs(u64::from(self.rest_after_min) * 60)
+    }
+
+    fn rest_length(&self) -> Duration {
+        Duration::from_secs(u64::from(self.rest_length_min) * 60)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BreakKind {
+    Micro,
+    Rest,
+    DailyLimit,
+}
+
+impl BreakKind {
+    pub fn message(&self) -> &'static str {
+        match self {
+            BreakKind::Micro => {
+                "Time for a micro-break: let go of the keyboard and relax your hands"
+            }
+            BreakKind::Rest => "Time for a rest break: stand up, stretch and look away",
+            BreakKind::DailyLimit => "You reached your daily keystroke limit",
+        }
+    }
+}
+
+/// Follows the presses to tell when a break is due
+#[derive(Default)]
+pub struct BreakTracker {
+    // first press after the last micro and rest break
+    micro_since: Option<SystemTime>,
+    rest_since: Option<SystemTime>,
+    presses_since_rest: u32,
+    last_press: Option<SystemTime>,
+    // local day `presses_today` counts
+    day: Option<NaiveDate>,
+    presses_today: u32,
+    // whether the daily limit was reported on `day`
+    limit_reported: bool,
+    // breaks raised and not taken or skipped yet, the one shown first
+    due: Vec<BreakKind>,
+}
+
+impl BreakTracker {
+    /// Starts the daily count with presses recorded earlier today
+    pub fn seed_today(&mut self, presses: u32, now: SystemTime) {
+        self.day = Some(DateTime::<Local>::from(now).date_naive());
+        self.presses_today = presses;
+        self.limit_reported = false;
+    }
+
+    /// Counts a press that was not an auto-repeat, returns the break that
+    /// became due with it
+    pub fn key_press(&mut self, settings: &BreakSettings, time: SystemTime) -> Option<BreakKind> {
+        let day = DateTime::<Local>::from(time).date_naive();
+        if self.day != Some(day) {
+            self.day = Some(day);
+            self.presses_today = 0;
+            self.limit_reported = false;
+        }
+        self.presses_today += 1;
+        self.presses_since_rest += 1;
+
+        // a long enough pause before this press was a break
+        let pause = self
+            .last_press
+            .and_then(|last| time.duration_since(last).ok());
+        self.last_press = Some(time);
+        if pause.is_none_or(|pause| pause >= settings.rest_length()) {
+            self.rest_since = Some(time);
+            self.presses_since_rest = 1;
+            self.due.retain(|&due| due != BreakKind::Rest);
+        }
+        if pause.is_none_or(|pause| pause >= settings.micro_length()) {
+            self.micro_since = Some(time);
+            self.due.retain(|&due| due != BreakKind::Micro);
+        }
+
+        if !settings.enabled {
+            return None;
+        }
+        let typed_for = |since: Option<SystemTime>| {
+            since
+                .and_then(|since| time.duration_since(since).ok())
+                .unwrap_or_default()
+        };
+        let mut raised = None;
+        // the limit may have been lowered below today's presses
+        if settings.daily_limit > 0
+            && self.presses_today >= settings.daily_limit
+            && !self.limit_reported
+        {
+            self.limit_reported = true;
+            raised = raised.or(self.raise(BreakKind::DailyLimit));
+        }
+        if typed_for(self.rest_since) >= settings.rest_after()
+            || (settings.rest_after_presses > 0
+                && self.presses_since_rest >= settings.rest_after_presses)
+        {
+            raised = raised.or(self.raise(BreakKind::Rest));
+        } else if typed_for(self.micro_since) >= settings.micro_after() {
+            raised = raised.or(self.raise(BreakKind::Micro));
+        }
+        raised
+    }
+
+    // queues `kind` unless it is queued already, a rest break stands in for a
+    // micro-break and both are shown before the daily limit
+    fn raise(&mut self, kind: BreakKind) -> Option<BreakKind> {
+        if self.due.contains(&kind)
+            || (kind == BreakKind::Micro && self.due.contains(&BreakKind::Rest))
+        {
+            return None;
+        }
+        if kind == BreakKind::Rest {
+            self.due.retain(|&due| due != BreakKind::Micro);
+        }
+        let at = match kind {
+            BreakKind::DailyLimit => self.due.len(),
+            _ => self
+                .due
+                .iter()
+                .position(|&due| due == BreakKind::DailyLimit)
+                .unwrap_or(self.due.len()),
+        };
+        self.due.insert(at, kind);
+        Some(kind)
+    }
+
+    /// Break shown at `now`, a micro or rest break stops being due once the
+    /// pause has lasted long enough
+    pub fn due(&self, settings: &BreakSettings, now: SystemTime) -> Option<BreakKind> {
+        if !settings.enabled {
+            return None;
+        }
+        self.due
+            .iter()
+            .copied()
+            .find(|&due| self.left(settings, due, now) != Some(Duration::ZERO))
+    }
+
+    /// Pause still needed at `now` for the shown break to count as taken
+    pub fn break_left(&self, settings: &BreakSettings, now: SystemTime) -> Option<Duration> {
+        self.left(settings, self.due(settings, now)?, now)
+    }
+
+    fn left(&self, settings: &BreakSettings, kind: BreakKind, now: SystemTime) -> Option<Duration> {
+        let length = match kind {
+            BreakKind::Micro => settings.micro_length(),
+            BreakKind::Rest => settings.rest_length(),
+            BreakKind::DailyLimit => return None,
+        };
+        let paused = self
+            .last_press
+            .and_then(|last| now.duration_since(last).ok())
+            .unwrap_or_default();
+        Some(length.saturating_sub(paused))
+    }
+
+    /// Dismisses the shown break, its timer starts over from `now`
+    pub fn skip(&mut self, settings: &BreakSettings, now: SystemTime) {
+        let shown = self.due(settings, now);
+        self.due.retain(|&due| Some(due) != shown);
+        match shown {
+            Some(BreakKind::Rest) => {
+                self.rest_since = Some(now);
+                self.presses_since_rest = 0;
+                self.micro_since = Some(now);
+            }
+            Some(BreakKind::Micro) => self.micro_since = Some(now),
+            Some(BreakKind::DailyLimit) | None => {}
+        }
+    }
+
+    pub fn presses_today(&self) -> u32 {
+        self.presses_today
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    fn settings() -> BreakSettings {
+        BreakSettings {
+            enabled: true,
+            ..BreakSettings::default()
+        }
+    }
+
+    #[test]
+    fn continuous_typing_makes_a_micro_break_due() {
+        let settings = settings();
+        let mut tracker = BreakTracker::default();
+        let mut raised = Vec::new();
+        // a press every ten seconds for eleven minutes
+        for secs in (0..=660).step_by(10) {
+            raised.extend(tracker.key_press(&settings, at(secs)));
+        }
+        assert_eq!(raised, [BreakKind::Micro]);
+        assert_eq!(tracker.due(&settings, at(665)), Some(BreakKind::Micro));
+        // the pause long enough, the break is taken
+        assert_eq!(tracker.due(&settings, at(700)), None);
+        assert_eq!(tracker.key_press(&settings, at(700)), None);
+    }
+
+    #[test]
+    fn pauses_restart_the_timers() {
+        let settings = settings();
+        let mut tracker = BreakTracker::default();
+        for secs in (0..=540).step_by(10) {
+            tracker.key_press(&settings, at(secs));
+        }
+        // a 40 second pause before the tenth minute
+        for secs in (580..=1100).step_by(10) {
+            assert_eq!(tracker.key_press(&settings, at(secs)), None);
+        }
+    }
+
+    #[test]
+    fn daily_limit_is_reported_once() {
+        let settings = BreakSettings {
+            daily_limit: 3,
+            ..settings()
+        };
+        let mut tracker = BreakTracker::default();
+        tracker.seed_today(1, at(0));
+        assert_eq!(tracker.key_press(&settings, at(1)), None);
+        assert_eq!(
+            tracker.key_press(&settings, at(2)),
+            Some(BreakKind::DailyLimit)
+        );
+        tracker.skip(&settings, at(3));
+        assert_eq!(tracker.key_press(&settings, at(4)), None);
+        assert_eq!(tracker.presses_today(), 4);
+    }
+
+    #[test]
+    fn daily_limit_already_passed_is_reported() {
+        let settings = BreakSettings {
+            daily_limit: 3,
+            ..settings()
+        };
+        let mut tracker = BreakTracker::default();
+        tracker.seed_today(5, at(0));
+        assert_eq!(
+            tracker.key_press(&settings, at(1)),
+            Some(BreakKind::DailyLimit)
+        );
+        assert_eq!(tracker.key_press(&settings, at(2)), None);
+    }
+
+    #[test]
+    fn breaks_are_raised_while_the_limit_is_shown() {
+        let settings = BreakSettings {
+            daily_limit: 1,
+            ..settings()
+        };
+        let mut tracker = BreakTracker::default();
+        let mut raised = Vec::new();
+        for secs in (0..=660).step_by(10) {
+            raised.extend(tracker.key_press(&settings, at(secs)));
+        }
+        assert_eq!(raised, [BreakKind::DailyLimit, BreakKind::Micro]);
+        // the micro-break is shown first, the limit once it is taken
+        assert_eq!(tracker.due(&settings, at(665)), Some(BreakKind::Micro));
+        assert_eq!(tracker.due(&settings, at(700)), Some(BreakKind::DailyLimit));
+        tracker.skip(&settings, at(700));
+        assert_eq!(tracker.due(&settings, at(700)), None);
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::press_time_map::{key_from_id, key_to_id};
+
+/// Modifier keys, left and right are not told apart
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Deserialize, Serialize)]
+pub enum Modifier {
+    Ctrl,
+    Alt,
+    Shift,
+    Meta,
+}
+
+impl Modifier {
+    pub const ALL: [Modifier; 4] = [
+        Modifier::Ctrl,
+        Modifier::Alt,
+        Modifier::Shift,
+        Modifier::Meta,
+    ];
+
+    pub fn from_key(key: rdev::Key) -> Option<Self> {
+        match key {
+            rdev::Key::ControlLeft | rdev::Key::ControlRight => Some(Modifier::Ctrl),
+            rdev::Key::Alt => Some(Modifier::Alt),
+            rdev::Key::ShiftLeft | rdev::Key::ShiftRight => Some(Modifier::Shift),
+            rdev::Key::MetaLeft | rdev::Key::MetaRight => Some(Modifier::Meta),
+            _ => None,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Modifier::Ctrl => "Ctrl",
+            #[cfg(target_os = "macos")]
+            Modifier::Alt => "Option",
+            #[cfg(not(target_os = "macos"))]
+            Modifier::Alt => "Alt",
+            Modifier::Shift => "Shift",
+            #[cfg(target_os = "macos")]
+            Modifier::Meta => "Cmd",
+            #[cfg(target_os = "windows")]
+            Modifier::Meta => "Win",
+            #[cfg(all(not(target_os = "windows"), not(target_os = "macos")))]
+            Modifier::Meta => "Super",
+        }
+    }
+
+    fn bit(&self) -> u8 {
+        1 << (*self as u8)
+    }
+}
+
+/// Set of modifiers held down at the same time
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+pub struct Modifiers(u8);
+
+impl Modifiers {
+    pub fn contains(&self, modifier: Modifier) -> bool {
+        self.0 & modifier.bit() != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// One bit per `Modifier`, as kept in an event store
+    pub fn bits(&self) -> u8 {
+        self.0
+    }
+
+    pub fn from_bits(bits: u8) -> Self {
+        Self(
+            bits & Modifier::ALL
+                .iter()
+                .fold(0, |all, modifier| all | modifier.bit()),
+        )
+    }
+
+    pub fn is_shift_only(&self) -> bool {
+        *self == Modifiers(Modifier::Shift.bit())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Modifier> + '_ {
+        Modifier::ALL
+            .into_iter()
+            .filter(|modifier| self.contains(*modifier))
+    }
+
+    /// Follows the modifier keys of a keyboard event
+    pub fn update(&mut self, event_type: &rdev::EventType) {
+        match *event_type {
+            rdev::EventType::KeyPress(key) => {
+                if let Some(modifier) = Modifier::from_key(key) {
+                    self.0 |= modifier.bit();
+                }
+            }
+            rdev::EventType::KeyRelease(key) => {
+                if let Some(modifier) = Modifier::from_key(key) {
+                    self.0 &= !modifier.bit();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl FromIterator<Modifier> for Modifiers {
+    fn from_iter<T: IntoIterator<Item = Modifier>>(iter: T) -> Self {
+        Modifiers(
+            iter.into_iter()
+                .fold(0, |bits, modifier| bits | modifier.bit()),
+        )
+    }
+}
+
+/// Shortcut usage and keys pressed under each modifier
+#[derive(Clone, Default)]
+pub struct ChordStats {
+    chords: HashMap<(Modifiers, rdev::Key), u32>,
+    held: HashMap<(Modifier, rdev::Key), u32>,
+}
+
+impl ChordStats {
+    /// Counts a press of a non-modifier key, `types_text` tells Shift chords
+    /// that only type a character apart from shortcuts
+    pub fn key_press(&mut self, key: rdev::Key, modifiers: Modifiers, types_text: bool) {
+        if Modifier::from_key(key).is_some() || modifiers.is_empty() {
+            return;
+        }
+        for modifier in modifiers.iter() {
+            *self.held.entry((modifier, key)).or_insert(0) += 1;
+        }
+        if !(modifiers.is_shift_only() && types_text) {
+            *self.chords.entry((modifiers, key)).or_insert(0) += 1;
+        }
+    }
+
+    /// Presses of `key` while `modifier` was held
+    pub fn get_held_times(&self, modifier: Modifier, key: rdev::Key) -> u32 {
+        self.held.get(&(modifier, key)).copied().unwrap_or(0)
+    }
+
+    pub fn top_chords(&self, count: usize) -> Vec<(Modifiers, rdev::Key, u32)> {
+        let mut chords: Vec<_> = self
+            .chords
+            .iter()
+            .map(|(&(modifiers, key), &times)| (modifiers, key, times))
+            .collect();
+        chords.sort_by(|a, b| {
+            b.2.cmp(&a.2)
+                .then_with(|| (a.0, key_to_id(a.1)).cmp(&(b.0, key_to_id(b.1))))
+        });
+        chords.truncate(count);
+        chords
+    }
+
+    pub fn clear(&mut self) {
+        self.chords.clear();
+        self.held.clear();
+    }
+
+    pub fn persisted_entries(&self) -> PersistedChords {
+        let mut chords: Vec<_> = self
+            .chords
+            .iter()
+            .map(|(&(modifiers, key), &count)| (modifiers.iter().collect(), key_to_id(key), count))
+            .collect();
+        chords.sort();
+        let mut held: Vec<_> = self
+            .held
+            .iter()
+            .map(|(&(modifier, key), &count)| (modifier, key_to_id(key), count))
+            .collect();
+        held.sort();
+        PersistedChords { chords, held }
+    }
+
+    pub fn restore_entries(&mut self, entries: PersistedChords) {
+        for (modifiers, id, count) in entries.chords {
+            if let Some(key) = key_from_id(&id) {
+                self.chords
+                    .insert((modifiers.into_iter().collect(), key), count);
+            }
+        }
+        for (modifier, id, count) in entries.held {
+            if let Some(key) = key_from_id(&id) {
+                self.held.insert((modifier, key), count);
+            }
+        }
+    }
+}
+
+#[derive(Default, Deserialize, Serialize)]
+pub struct PersistedChords {
+    #[serde(default)]
+    chords: Vec<(Vec<Modifier>, String, u32)>,
+    #[serde(default)]
+    held: Vec<(Modifier, String, u32)>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modifiers_follow_presses_and_releases() {
+        let mut modifiers = Modifiers::default();
+        modifiers.update(&rdev::EventType::KeyPress(rdev::Key::ControlLeft));
+        modifiers.update(&rdev::EventType::KeyPress(rdev::Key::ShiftRight));
+        modifiers.update(&rdev::EventType::KeyRelease(rdev::Key::ControlLeft));
+
+        assert!(modifiers.is_shift_only());
+        assert_eq!(modifiers.iter().collect::<Vec<_>>(), [Modifier::Shift]);
+    }
+
+    #[test]
+    fn shift_only_typing_is_not_a_chord() {
+        let ctrl_shift: Modifiers = [Modifier::Ctrl, Modifier::Shift].into_iter().collect();
+        let shift: Modifiers = [Modifier::Shift].into_iter().collect();
+        let mut stats = ChordStats::default();
+        stats.key_press(rdev::Key::KeyT, ctrl_shift, false);
+        stats.key_press(rdev::Key::KeyT, ctrl_shift, false);
+        stats.key_press(rdev::Key::KeyA, shift, true);
+        stats.key_press(rdev::Key::Tab, shift, false);
+        stats.key_press(rdev::Key::KeyB, Modifiers::default(), true);
+
+        assert_eq!(stats.top_chords(5).len(), 2);
+        assert_eq!(stats.get_held_times(Modifier::Shift, rdev::Key::KeyA), 1);
+        assert_eq!(stats.get_held_times(Modifier::Ctrl, rdev::Key::KeyT), 2);
+
+        let mut restored = ChordStats::default();
+        restored.restore_entries(stats.persisted_entries());
+        assert_eq!(restored.top_chords(1)[0].0, ctrl_shift);
+    }
+}
@@ -0,0 +1,727 @@
+use std::{
+    error::Error,
+    fs,
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use chrono::{Local, NaiveDateTime, TimeZone};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chord::Modifiers,
+    history::PersistedBucket,
+    logical_layout::LogicalLayout,
+    mouse::MouseInput,
+    press_time_map::{key_from_id, key_to_id, PressTimesMap},
+};
+
+type StoreResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+
+const JSON_EVENT_FILE: &str = "events.jsonl";
+const SQLITE_EVENT_FILE: &str = "events.sqlite3";
+
+// bucket starts are compared as text, this format sorts like the times
+const BUCKET_START_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+const SQLITE_SCHEMA: &str = "
+    PRAGMA journal_mode = WAL;
+    CREATE TABLE IF NOT EXISTS events (
+        time_ms INTEGER NOT NULL,
+        -- 'press', 'release' or 'mouse'
+        kind TEXT NOT NULL,
+        -- key id, or the mouse input
+        input TEXT NOT NULL,
+        modifiers INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE INDEX IF NOT EXISTS events_by_time ON events (time_ms);
+    CREATE TABLE IF NOT EXISTS imported_buckets (
+        start TEXT NOT NULL,
+        bucket TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS imported_buckets_by_start ON imported_buckets (start);
+";
+
+/// Where every recorded event is kept, set in the ☰ menu. Nothing is kept
+/// until a store is picked
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Deserialize, Serialize)]
+pub enum EventStoreKind {
+    #[default]
+    Off,
+    JsonLines,
+    Sqlite,
+}
+
+impl EventStoreKind {
+    pub const ALL: [EventStoreKind; 3] = [
+        EventStoreKind::Off,
+        EventStoreKind::JsonLines,
+        EventStoreKind::Sqlite,
+    ];
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            EventStoreKind::Off => "Off",
+            EventStoreKind::JsonLines => "JSON lines",
+            EventStoreKind::Sqlite => "SQLite",
+        }
+    }
+
+    /// Opens the store of this kind in `dir`, `None` when events are not kept
+    pub fn open(&self, dir: &Path) -> StoreResult<Option<Box<dyn EventStore>>> {
+        if *self != EventStoreKind::Off {
+            fs::create_dir_all(dir)?;
+        }
+        Ok(match self {
+            EventStoreKind::Off => None,
+            EventStoreKind::JsonLines => {
+                Some(Box::new(JsonEventStore::open(dir.join(JSON_EVENT_FILE))))
+            }
+            EventStoreKind::Sqlite => Some(Box::new(SqliteEventStore::open(
+                &dir.join(SQLITE_EVENT_FILE),
+            )?)),
+        })
+    }
+}
+
+/// Input of a recorded event, the text a press typed is never stored
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+pub enum RecordedInput {
+    /// key id as in `key_to_id`
+    KeyPress {
+        key: String,
+    },
+    KeyRelease {
+        key: String,
+    },
+    Mouse(MouseInput),
+}
+
+/// Input event as it is kept in an event store
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+pub struct RecordedEvent {
+    /// milliseconds since the Unix epoch
+    pub time_ms: i64,
+    pub input: RecordedInput,
+    /// `Modifiers::bits` of the modifiers held down
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub modifiers: u8,
+}
+
+fn is_zero(value: &u8) -> bool {
+    *value == 0
+}
+
+impl RecordedEvent {
+    /// Events kept for an input event, a wheel event can scroll both ways
+    pub fn from_event(event: &rdev::Event, modifiers: Modifiers) -> Vec<Self> {
+        let time_ms = event
+            .time
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |since| since.as_millis() as i64);
+        let inputs: Vec<_> = match event.event_type {
+            rdev::EventType::KeyPress(key) => vec![RecordedInput::KeyPress {
+                key: key_to_id(key),
+            }],
+            rdev::EventType::KeyRelease(key) => vec![RecordedInput::KeyRelease {
+                key: key_to_id(key),
+            }],
+            rdev::EventType::ButtonPress(button) => MouseInput::from_button(button)
+                .map(RecordedInput::Mouse)
+                .into_iter()
+                .collect(),
+            rdev::EventType::Wheel { delta_x, delta_y } => MouseInput::from_wheel(delta_x, delta_y)
+                .map(RecordedInput::Mouse)
+                .collect(),
+            _ => Vec::new(),
+        };
+        inputs
+            .into_iter()
+            .map(|input| Self {
+                time_ms,
+                input,
+                modifiers: modifiers.bits(),
+            })
+            .collect()
+    }
+
+    pub fn time(&self) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_millis(self.time_ms.max(0) as u64)
+    }
+
+    /// Counts a live event into `press_map` with the text its press typed,
+    /// returns whether it was an auto-repeated press
+    pub fn apply_typed(&self, press_map: &mut PressTimesMap, text: Option<&str>) -> bool {
+        self.count(press_map, text, text.is_some())
+    }
+
+    /// Counts a replayed event into `press_map`. Without the typed text no
+    /// characters are counted, and the keys of the character block stand in
+    /// for the presses that typed text
+    pub fn apply(&self, press_map: &mut PressTimesMap) -> bool {
+        let types_text = match &self.input {
+            RecordedInput::KeyPress { key } => key_from_id(key)
+                .is_some_and(|key| LogicalLayout::default().characters(key).is_some()),
+            _ => false,
+        };
+        self.count(press_map, None, types_text)
+    }
+
+    // live and replayed events are counted alike
+    fn count(&self, press_map: &mut PressTimesMap, text: Option<&str>, types_text: bool) -> bool {
+        let time = self.time();
+        match &self.input {
+            RecordedInput::KeyPress { key } => {
+                let Some(key) = key_from_id(key) else {
+                    return false;
+                };
+                let modifiers = Modifiers::from_bits(self.modifiers);
+                // chords and characters typed by auto-repeat are not counted
+                let auto_repeat = press_map.key_press(key, time);
+                if !auto_repeat {
+                    press_map.chord_press(key, modifiers, types_text);
+                    if let Some(text) = text {
+                        press_map.char_press(text, time);
+                    }
+                }
+                // held Backspace deletes a character per auto-repeat
+                press_map.correction_press(key, modifiers, types_text, time);
+                auto_repeat
+            }
+            RecordedInput::KeyRelease { key } => {
+                if let Some(key) = key_from_id(key) {
+                    press_map.key_release(key, time);
+                }
+                false
+            }
+            RecordedInput::Mouse(input) => {
+                press_map.mouse_press(*input, time);
+                false
+            }
+        }
+    }
+}
+
+/// Keeps every recorded event, so any span of time can be counted exactly
+pub trait EventStore: Send {
+    fn append(&mut self, events: &[RecordedEvent]) -> StoreResult<()>;
+
+    /// Keeps history counted before events were stored, each bucket stands in
+    /// for the events of its span
+    fn import_buckets(&mut self, buckets: &[PersistedBucket]) -> StoreResult<()>;
+
+    /// Whether nothing was appended or imported yet
+    fn is_empty(&self) -> StoreResult<bool>;
+
+    /// Events in `[from, to)` local time, oldest first, and the imported
+    /// buckets starting in it
+    fn query(
+        &self,
+        from: NaiveDateTime,
+        to: NaiveDateTime,
+    ) -> StoreResult<(Vec<RecordedEvent>, Vec<PersistedBucket>)>;
+}
+
+/// Counts of `[from, to)` rebuilt from `store`, unlike
+/// `PressTimesMap::in_range` hold times, n-grams and chords are only counted
+/// in the range as well. Characters are only counted for imported buckets
+pub fn press_map_between(
+    store: &dyn EventStore,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+    idle_gap: Duration,
+) -> StoreResult<PressTimesMap> {
+    let (events, buckets) = store.query(from, to)?;
+    let mut press_map = PressTimesMap::new();
+    press_map.ngrams.idle_gap = idle_gap;
+    press_map.add_history(buckets);
+    for event in &events {
+        event.apply(&mut press_map);
+    }
+    Ok(press_map)
+}
+
+/// Milliseconds since the Unix epoch of a local time, the earlier one when
+/// the clocks go back
+fn local_ms(time: NaiveDateTime) -> i64 {
+    Local.from_local_datetime(&time).earliest().map_or_else(
+        || time.and_utc().timestamp_millis(),
+        |time| time.timestamp_millis(),
+    )
+}
+
+#[derive(Deserialize, Serialize)]
+enum JsonLine {
+    Event(RecordedEvent),
+    Imported(PersistedBucket),
+}
+
+/// Events appended to a file, one JSON object per line
+pub struct JsonEventStore {
+    path: PathBuf,
+}
+
+impl JsonEventStore {
+    pub fn open(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    fn append_lines(&self, lines: impl Iterator<Item = JsonLine>) -> StoreResult<()> {
+        let mut bytes = Vec::new();
+        for line in lines {
+            serde_json::to_writer(&mut bytes, &line)?;
+            bytes.push(b'\n');
+        }
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&self.path)?;
+        // a line cut short by a crash is ended, so the first new line stays whole
+        if file.metadata()?.len() > 0 {
+            let mut last = [0];
+            file.seek(SeekFrom::End(-1))?;
+            file.read_exact(&mut last)?;
+            if last != *b"\n" {
+                bytes.insert(0, b'\n');
+            }
+        }
+        file.write_all(&bytes)?;
+        file.sync_data()?;
+        Ok(())
+    }
+}
+
+impl EventStore for JsonEventStore {
+    fn append(&mut self, events: &[RecordedEvent]) -> StoreResult<()> {
+        self.append_lines(events.iter().cloned().map(JsonLine::Event))
+    }
+
+    fn import_buckets(&mut self, buckets: &[PersistedBucket]) -> StoreResult<()> {
+        self.append_lines(buckets.iter().cloned().map(JsonLine::Imported))
+    }
+
+    fn is_empty(&self) -> StoreResult<bool> {
+        match fs::metadata(&self.path) {
+            Ok(metadata) => Ok(metadata.len() == 0),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(true),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn query(
+        &self,
+        from: NaiveDateTime,
+        to: NaiveDateTime,
+    ) -> StoreResult<(Vec<RecordedEvent>, Vec<PersistedBucket>)> {
+        let file = match fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Default::default()),
+            Err(err) => return Err(err.into()),
+        };
+        let (from_ms, to_ms) = (local_ms(from), local_ms(to));
+        let mut events = Vec::new();
+        let mut buckets = Vec::new();
+        for line in BufReader::new(file).lines() {
+            // a line cut short by a crash is skipped
+            match serde_json::from_str(&line?) {
+                Ok(JsonLine::Event(event)) if (from_ms..to_ms).contains(&event.time_ms) => {
+                    events.push(event);
+                }
+                Ok(JsonLine::Imported(bucket)) if (from..to).contains(&bucket.start()) => {
+                    buckets.push(bucket);
+                }
+                _ => {}
+            }
+        }
+        events.sort_by_key(|event| event.time_ms);
+        Ok((events, buckets))
+    }
+}
+
+/// Events kept in a SQLite database, hold times are replayed from the releases
+pub struct SqliteEventStore {
+    connection: Connection,
+}
+
+impl SqliteEventStore {
+    pub fn open(path: &Path) -> StoreResult<Self> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(SQLITE_SCHEMA)?;
+        Ok(Self { connection })
+    }
+}
+
+impl EventStore for SqliteEventStore {
+    fn append(&mut self, events: &[RecordedEvent]) -> StoreResult<()> {
+        let transaction = self.connection.transaction()?;
+        {
+            let mut insert = transaction.prepare_cached(
+                "INSERT INTO events (time_ms, kind, input, modifiers)
+                 VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for event in events {
+                let (kind, input) = match &event.input {
+                    RecordedInput::KeyPress { key } => ("press", key.clone()),
+                    RecordedInput::KeyRelease { key } => ("release", key.clone()),
+                    RecordedInput::Mouse(input) => ("mouse", mouse_id(*input)),
+                };
+                insert.execute(params![event.time_ms, kind, input, event.modifiers])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn import_buckets(&mut self, buckets: &[PersistedBucket]) -> StoreResult<()> {
+        let transaction = self.connection.transaction()?;
+        {
+            let mut insert = transaction
+                .prepare_cached("INSERT INTO imported_buckets (start, bucket) VALUES (?1, ?2)")?;
+            for bucket in buckets {
+                insert.execute(params![
+                    bucket.start().format(BUCKET_START_FORMAT).to_string(),
+                    serde_json::to_string(bucket)?
+                ])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn is_empty(&self) -> StoreResult<bool> {
+        let stored: bool = self.connection.query_row(
+            "SELECT EXISTS (SELECT 1 FROM events) OR EXISTS (SELECT 1 FROM imported_buckets)",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(!stored)
+    }
+
+    fn query(
+        &self,
+        from: NaiveDateTime,
+        to: NaiveDateTime,
+    ) -> StoreResult<(Vec<RecordedEvent>, Vec<PersistedBucket>)> {
+        let mut statement = self.connection.prepare(
+            "SELECT time_ms, kind, input, modifiers FROM events
+             WHERE time_ms >= ?1 AND time_ms < ?2 ORDER BY time_ms, rowid",
+        )?;
+        let rows = statement.query_map(params![local_ms(from), local_ms(to)], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, u8>(3)?,
+            ))
+        })?;
+        let mut events = Vec::new();
+        for row in rows {
+            let (time_ms, kind, input, modifiers) = row?;
+            let input = match kind.as_str() {
+                "press" => RecordedInput::KeyPress { key: input },
+                "release" => RecordedInput::KeyRelease { key: input },
+                "mouse" => match mouse_from_id(&input) {
+                    Some(input) => RecordedInput::Mouse(input),
+                    None => continue,
+                },
+                _ => continue,
+            };
+            events.push(RecordedEvent {
+                time_ms,
+                input,
+                modifiers,
+            });
+        }
+
+        let mut statement = self.connection.prepare(
+            "SELECT bucket FROM imported_buckets WHERE start >= ?1 AND start < ?2 ORDER BY start",
+        )?;
+        let rows = statement.query_map(
+            params![
+                from.format(BUCKET_START_FORMAT).to_string(),
+                to.format(BUCKET_START_FORMAT).to_string()
+            ],
+            |row| row.get::<_, String>(0),
+        )?;
+        let mut buckets = Vec::new();
+        for row in rows {
+            buckets.push(serde_json::from_str(&row?)?);
+        }
+        Ok((events, buckets))
+    }
+}
+
+// mouse inputs are stored under their serde names
+fn mouse_id(input: MouseInput) -> String {
+    match serde_json::to_value(input) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => String::new(),
+    }
+}
+
+fn mouse_from_id(id: &str) -> Option<MouseInput> {
+    serde_json::from_value(serde_json::Value::String(id.to_string())).ok()
+}
+
+/// Recorded events waiting to be written to the chosen store
+#[derive(Default)]
+pub struct EventLog {
+    kind: EventStoreKind,
+    dir: PathBuf,
+    // locked on its own, so events are written without the log locked
+    store: Option<Arc<Mutex<Box<dyn EventStore>>>>,
+    pending: Vec<RecordedEvent>,
+}
+
+impl EventLog {
+    /// Opens the store of `kind` in `dir`, a store that is still empty imports
+    /// `history` so the counts from before it existed can be queried too. A
+    /// store that was used before imports nothing, the counts recorded while
+    /// another store was picked are missing from its ranges
+    pub fn open(
+        kind: EventStoreKind,
+        dir: &Path,
+        history: &[PersistedBucket],
+    ) -> StoreResult<Self> {
+        let mut store = kind.open(dir)?;
+        if let Some(store) = &mut store {
+            if store.is_empty()? && !history.is_empty() {
+                store.import_buckets(history)?;
+            }
+        }
+        Ok(Self {
+            kind,
+            dir: dir.to_path_buf(),
+            store: store.map(|store| Arc::new(Mutex::new(store))),
+            pending: Vec::new(),
+        })
+    }
+
+    pub fn push(&mut self, events: Vec<RecordedEvent>) {
+        if self.store.is_some() {
+            self.pending.extend(events);
+        }
+    }
+
+    /// Writes the pending events of `log`. The log is only locked to take
+    /// them, so events keep being pushed while they are written, and they are
+    /// put back for the next try if the write fails
+    pub fn flush(log: &Mutex<EventLog>) -> StoreResult<()> {
+        let Some(store) = log.lock().unwrap().store.clone() else {
+            return Ok(());
+        };
+        // flushes write one after the other, in the order they took events
+        let mut writer = store.lock().unwrap();
+        let events = std::mem::take(&mut log.lock().unwrap().pending);
+        if events.is_empty() {
+            return Ok(());
+        }
+        let written = writer.append(&events);
+        if written.is_err() {
+            let mut log = log.lock().unwrap();
+            // a log switched to another store meanwhile does not take them
+            if log
+                .store
+                .as_ref()
+                .is_some_and(|current| Arc::ptr_eq(current, &store))
+            {
+                let newer = std::mem::replace(&mut log.pending, events);
+                log.pending.extend(newer);
+            }
+        }
+        written
+    }
+
+    /// Writes the pending events of `log` and opens another handle on its
+    /// store, so it can be queried without the log locked. `None` when
+    /// events are not kept
+    pub fn reader(log: &Mutex<EventLog>) -> StoreResult<Option<Box<dyn EventStore>>> {
+        Self::flush(log)?;
+        let (kind, dir) = {
+            let log = log.lock().unwrap();
+            (log.kind, log.dir.clone())
+        };
+        kind.open(&dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(hour: u32, minute: u32) -> NaiveDateTime {
+        chrono::NaiveDate::from_ymd_opt(2026, 10, 17)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    fn press(at: NaiveDateTime, key: &str) -> RecordedEvent {
+        RecordedEvent {
+            time_ms: local_ms(at),
+            input: RecordedInput::KeyPress {
+                key: key.to_string(),
+            },
+            modifiers: 0,
+        }
+    }
+
+    fn release(at: NaiveDateTime, key: &str) -> RecordedEvent {
+        RecordedEvent {
+            time_ms: local_ms(at) + 80,
+            input: RecordedInput::KeyRelease {
+                key: key.to_string(),
+            },
+            modifiers: 0,
+        }
+    }
+
+    fn stores(name: &str) -> (PathBuf, Vec<Box<dyn EventStore>>) {
+        let dir = std::env::temp_dir().join(format!(
+            "keyboard-heatmap-events-{name}-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        let stores = [EventStoreKind::JsonLines, EventStoreKind::Sqlite]
+            .into_iter()
+            .map(|kind| kind.open(&dir).unwrap().unwrap())
+            .collect();
+        (dir, stores)
+    }
+
+    #[test]
+    fn ranges_replay_only_their_events() {
+        let (dir, stores) = stores("ranges");
+        for mut store in stores {
+            assert!(store.is_empty().unwrap());
+            store
+                .append(&[
+                    press(time(9, 0), "KeyA"),
+                    release(time(9, 0), "KeyA"),
+                    press(time(10, 0), "KeyB"),
+                    release(time(10, 0), "KeyB"),
+                    RecordedEvent {
+                        time_ms: local_ms(time(10, 5)),
+                        input: RecordedInput::Mouse(MouseInput::Left),
+                        modifiers: 0,
+                    },
+                ])
+                .unwrap();
+            assert!(!store.is_empty().unwrap());
+
+            let idle_gap = Duration::from_secs(1);
+            let morning =
+                press_map_between(store.as_ref(), time(8, 0), time(9, 30), idle_gap).unwrap();
+            assert_eq!(morning.get_key_times(rdev::Key::KeyA), 1);
+            assert_eq!(morning.get_key_times(rdev::Key::KeyB), 0);
+            // the typed text is not stored
+            assert_eq!(morning.get_char_times("a"), 0);
+            assert_eq!(
+                morning
+                    .hold_stats(rdev::Key::KeyA)
+                    .map(|hold| hold.mean_ms()),
+                Some(80)
+            );
+
+            let later =
+                press_map_between(store.as_ref(), time(9, 30), time(11, 0), idle_gap).unwrap();
+            assert_eq!(later.get_key_times(rdev::Key::KeyB), 1);
+            assert_eq!(later.get_mouse_times(MouseInput::Left), 1);
+            assert_eq!(later.total_presses(), 1);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn imported_history_counts_by_bucket_start() {
+        let mut history = PressTimesMap::new();
+        let at = Local.from_local_datetime(&time(9, 15)).unwrap();
+        history.key_press(rdev::Key::KeyQ, SystemTime::from(at));
+        let buckets = history.persisted_history();
+
+        let (dir, stores) = stores("import");
+        for mut store in stores {
+            store.import_buckets(&buckets).unwrap();
+            assert!(!store.is_empty().unwrap());
+            let idle_gap = Duration::from_secs(1);
+            let hour =
+                press_map_between(store.as_ref(), time(9, 0), time(10, 0), idle_gap).unwrap();
+            assert_eq!(hour.get_key_times(rdev::Key::KeyQ), 1);
+            let after =
+                press_map_between(store.as_ref(), time(10, 0), time(11, 0), idle_gap).unwrap();
+            assert_eq!(after.total_presses(), 0);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn appends_after_a_cut_short_line_stay_readable() {
+        let dir = std::env::temp_dir().join(format!(
+            "keyboard-heatmap-events-cut-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(JSON_EVENT_FILE);
+        fs::write(&path, "{\"Event\":{\"time_ms\":1").unwrap();
+        let mut store = JsonEventStore::open(path);
+        store.append(&[press(time(9, 0), "KeyA")]).unwrap();
+        let (events, _) = store.query(time(8, 0), time(10, 0)).unwrap();
+        assert_eq!(events.len(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    struct FailingStore;
+
+    impl EventStore for FailingStore {
+        fn append(&mut self, _events: &[RecordedEvent]) -> StoreResult<()> {
+            Err("disk full".into())
+        }
+
+        fn import_buckets(&mut self, _buckets: &[PersistedBucket]) -> StoreResult<()> {
+            Ok(())
+        }
+
+        fn is_empty(&self) -> StoreResult<bool> {
+            Ok(true)
+        }
+
+        fn query(
+            &self,
+            _from: NaiveDateTime,
+            _to: NaiveDateTime,
+        ) -> StoreResult<(Vec<RecordedEvent>, Vec<PersistedBucket>)> {
+            Ok(Default::default())
+        }
+    }
+
+    #[test]
+    fn failed_flush_keeps_the_events_in_order() {
+        let log = Mutex::new(EventLog {
+            store: Some(Arc::new(Mutex::new(Box::new(FailingStore)))),
+            ..EventLog::default()
+        });
+        log.lock().unwrap().push(vec![press(time(9, 0), "KeyA")]);
+        assert!(EventLog::flush(&log).is_err());
+        log.lock().unwrap().push(vec![press(time(9, 1), "KeyB")]);
+        assert!(EventLog::flush(&log).is_err());
+        let pending = &log.lock().unwrap().pending;
+        assert_eq!(pending.len(), 2);
+        assert!(pending[0].time_ms < pending[1].time_ms);
+    }
+
+    #[test]
+    fn auto_repeats_replay_as_repeats() {
+        let mut press_map = PressTimesMap::new();
+        let auto_repeats: Vec<_> = [0, 0, 2]
+            .into_iter()
+            .map(|minute| press(time(9, minute), "Backspace").apply(&mut press_map))
+            .collect();
+        assert_eq!(auto_repeats, [false, true, false]);
+        assert_eq!(press_map.get_repeat_times(rdev::Key::Backspace), 1);
+    }
+}
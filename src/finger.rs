@@ -0,0 +1,354 @@
+use std::collections::HashMap;
+
+use egui::{Align2, Color32, FontId, Rect, Sense, Ui, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    layout::KeyboardLayout,
+    press_time_map::{key_from_id, key_to_id, PressTimesMap},
+};
+
+/// Height of the finger load chart drawn beneath the keyboard
+pub const CHART_HEIGHT: f32 = 120.;
+
+const BAR_WIDTH: f32 = 36.;
+const BAR_GAP: f32 = 12.;
+// room left for the percentage above a bar and the finger name below it
+const BAR_LABEL_HEIGHT: f32 = 18.;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Deserialize, Serialize)]
+pub enum Finger {
+    LeftPinky,
+    LeftRing,
+    LeftMiddle,
+    LeftIndex,
+    LeftThumb,
+    RightThumb,
+    RightIndex,
+    RightMiddle,
+    RightRing,
+    RightPinky,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Hand {
+    Left,
+    Right,
+}
+
+impl Finger {
+    /// Left to right across both hands
+    pub const ALL: [Finger; 10] = [
+        Finger::LeftPinky,
+        Finger::LeftRing,
+        Finger::LeftMiddle,
+        Finger::LeftIndex,
+        Finger::LeftThumb,
+        Finger::RightThumb,
+        Finger::RightIndex,
+        Finger::RightMiddle,
+        Finger::RightRing,
+        Finger::RightPinky,
+    ];
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Finger::LeftPinky => "Left pinky",
+            Finger::LeftRing => "Left ring",
+            Finger::LeftMiddle => "Left middle",
+            Finger::LeftIndex => "Left index",
+            Finger::LeftThumb => "Left thumb",
+            Finger::RightThumb => "Right thumb",
+            Finger::RightIndex => "Right index",
+            Finger::RightMiddle => "Right middle",
+            Finger::RightRing => "Right ring",
+            Finger::RightPinky => "Right pinky",
+        }
+    }
+
+    fn short_name(&self) -> &'static str {
+        match self {
+            Finger::LeftPinky | Finger::RightPinky => "Pinky",
+            Finger::LeftRing | Finger::RightRing => "Ring",
+            Finger::LeftMiddle | Finger::RightMiddle => "Mid",
+            Finger::LeftIndex | Finger::RightIndex => "Index",
+            Finger::LeftThumb | Finger::RightThumb => "Thumb",
+        }
+    }
+
+    /// 0 for the pinky up to 4 for the thumb, the same on both hands
+    pub fn column(&self) -> u8 {
+        match self.hand() {
+            Hand::Left => *self as u8,
+            Hand::Right => Finger::RightPinky as u8 - *self as u8,
+        }
+    }
+
+    pub fn hand(&self) -> Hand {
+        if *self <= Finger::LeftThumb {
+            Hand::Left
+        } else {
+            Hand::Right
+        }
+    }
+
+    /// Both hands share a color per finger, they never sit next to each other
+    pub fn color(&self) -> Color32 {
+        match self {
+            Finger::LeftPinky | Finger::RightPinky => Color32::from_rgb(200, 60, 160),
+            Finger::LeftRing | Finger::RightRing => Color32::from_rgb(60, 110, 220),
+            Finger::LeftMiddle | Finger::RightMiddle => Color32::from_rgb(40, 160, 90),
+            Finger::LeftIndex | Finger::RightIndex => Color32::from_rgb(220, 170, 20),
+            Finger::LeftThumb | Finger::RightThumb => Color32::from_rgb(120, 120, 120),
+        }
+    }
+}
+
+impl Hand {
+    pub fn description(&self) -> &'static str {
+        match self {
+            Hand::Left => "Left",
+            Hand::Right => "Right",
+        }
+    }
+}
+
+/// Finger of the standard touch typing assignment on a row staggered board,
+/// `None` for keys it says nothing about
+pub fn default_finger(key: rdev::Key) -> Option<Finger> {
+    use rdev::Key::*;
+    let finger = match key {
+        Escape | F1 | BackQuote | Num1 | Tab | KeyQ | CapsLock | KeyA | ShiftLeft
+        | IntlBackslash | KeyZ | ControlLeft | Function => Finger::LeftPinky,
+        F2 | Num2 | KeyW | KeyS | KeyX => Finger::LeftRing,
+        F3 | Num3 | KeyE | KeyD | KeyC => Finger::LeftMiddle,
+        F4 | F5 | Num4 | Num5 | KeyR | KeyT | KeyF | KeyG | KeyV | KeyB => Finger::LeftIndex,
+        MetaLeft | Alt => Finger::LeftThumb,
+        Space | AltGr | MetaRight => Finger::RightThumb,
+        F6 | F7 | Num6 | Num7 | KeyY | KeyU | KeyH | KeyJ | KeyN | KeyM => Finger::RightIndex,
+        F8 | Num8 | KeyI | KeyK | Comma => Finger::RightMiddle,
+        F9 | Num9 | KeyO | KeyL | Dot => Finger::RightRing,
+        F10 | F11 | F12 | Num0 | Minus | Equal | Backspace | KeyP | LeftBracket | RightBracket
+        | BackSlash | SemiColon | Quote | Return | Slash | ShiftRight | ControlRight => {
+            Finger::RightPinky
+        }
+        // the navigation cluster and the numpad are worked by the right hand
+        PrintScreen | Insert | Delete | LeftArrow | NumLock | Kp7 | Kp4 | Kp1 | Kp0 => {
+            Finger::RightIndex
+        }
+        ScrollLock | Home | End | UpArrow | DownArrow | KpDivide | Kp8 | Kp5 | Kp2 => {
+            Finger::RightMiddle
+        }
+        Pause | PageUp | PageDown | RightArrow | KpMultiply | Kp9 | Kp6 | Kp3 | KpDelete => {
+            Finger::RightRing
+        }
+        KpMinus | KpPlus | KpReturn => Finger::RightPinky,
+        _ => return None,
+    };
+    Some(finger)
+}
+
+/// Fingers edited by the user for one keyboard, they win over the layout
+pub type FingerOverrides = HashMap<rdev::Key, Finger>;
+
+pub fn persisted_overrides(overrides: &FingerOverrides) -> Vec<(String, Finger)> {
+    let mut entries: Vec<_> = overrides
+        .iter()
+        .map(|(&key, &finger)| (key_to_id(key), finger))
+        .collect();
+    entries.sort();
+    entries
+}
+
+pub fn restore_overrides(entries: Vec<(String, Finger)>) -> FingerOverrides {
+    entries
+        .into_iter()
+        .filter_map(|(id, finger)| Some((key_from_id(&id)?, finger)))
+        .collect()
+}
+
+/// Finger pressing each key of a layout
+#[derive(Clone, Default)]
+pub struct FingerMap {
+    fingers: HashMap<rdev::Key, Finger>,
+}
+
+impl FingerMap {
+    /// User overrides come first, then the finger the layout file names, then
+    /// the standard assignment. A key placed twice takes its first placement,
+    /// like `KeyboardLayout::find`
+    pub fn new(layout: &KeyboardLayout, overrides: Option<&FingerOverrides>) -> Self {
+        let mut fingers = HashMap::new();
+        for layout_key in &layout.keys {
+            let Some(key) = layout_key.key else {
+                continue;
+            };
+            let finger = overrides
+                .and_then(|overrides| overrides.get(&key).copied())
+                .or(layout_key.finger)
+                .or_else(|| default_finger(key));
+            if let Some(finger) = finger {
+                fingers.entry(key).or_insert(finger);
+            }
+        }
+        Self { fingers }
+    }
+
+    pub fn get(&self, key: rdev::Key) -> Option<Finger> {
+        self.fingers.get(&key).copied()
+    }
+
+    /// Presses of the keys each finger is assigned to, auto-repeats left out
+    pub fn loads(&self, map: &PressTimesMap) -> FingerLoads {
+        let mut presses = [0; Finger::ALL.len()];
+        for (&key, &finger) in &self.fingers {
+            presses[finger as usize] += u64::from(map.get_key_times(key));
+        }
+        FingerLoads { presses }
+    }
+}
+
+pub struct FingerLoads {
+    // indexed like `Finger::ALL`
+    presses: [u64; 10],
+}
+
+impl FingerLoads {
+    pub fn total(&self) -> u64 {
+        self.presses.iter().sum()
+    }
+
+    /// Share of all assigned presses in `[0, 1]`
+    pub fn share(&self, finger: Finger) -> f32 {
+        match self.total() {
+            0 => 0.,
+            total => self.presses[finger as usize] as f32 / total as f32,
+        }
+    }
+
+    pub fn hand_share(&self, hand: Hand) -> f32 {
+        Finger::ALL
+            .iter()
+            .filter(|finger| finger.hand() == hand)
+            .map(|&finger| self.share(finger))
+            .sum()
+    }
+
+    /// Bars per finger, left hand first, with the hand split in between
+    pub fn draw_chart(&self, ui: &mut Ui) {
+        let text_color = Color32::from_rgb(32, 5, 64);
+        let bar_count = Finger::ALL.len() as f32;
+        // the gap between the thumbs is wider and holds the hand split
+        let middle_gap = 6. * BAR_GAP;
+        let width = bar_count * BAR_WIDTH + (bar_count - 2.) * BAR_GAP + middle_gap;
+        let (rect, _) = ui.allocate_exact_size(Vec2::new(width, CHART_HEIGHT), Sense::hover());
+        let painter = ui.painter();
+        let bar_bottom = rect.bottom() - BAR_LABEL_HEIGHT;
+        let max_height = CHART_HEIGHT - 2. * BAR_LABEL_HEIGHT;
+        // scaled to the busiest finger so small differences stay visible
+        let max_share = Finger::ALL
+            .iter()
+            .map(|&finger| self.share(finger))
+            .fold(0., f32::max);
+
+        let mut x = rect.left();
+        for finger in Finger::ALL {
+            let share = self.share(finger);
+            let height = if max_share > 0. {
+                max_height * share / max_share
+            } else {
+                0.
+            };
+            let bar = Rect::from_min_max(
+                egui::pos2(x, bar_bottom - height),
+                egui::pos2(x + BAR_WIDTH, bar_bottom),
+            );
+            painter.rect_filled(bar, 3., finger.color());
+            painter.text(
+                bar.center_top(),
+                Align2::CENTER_BOTTOM,
+                format!("{:.1}%", share * 100.),
+                FontId::monospace(11.),
+                text_color,
+            );
+            painter.text(
+                egui::pos2(bar.center().x, bar_bottom + 3.),
+                Align2::CENTER_TOP,
+                finger.short_name(),
+                FontId::monospace(11.),
+                text_color,
+            );
+            x += BAR_WIDTH
+                + if finger == Finger::LeftThumb {
+                    middle_gap
+                } else {
+                    BAR_GAP
+                };
+        }
+
+        let split_x = rect.left() + 5. * BAR_WIDTH + 4. * BAR_GAP + middle_gap / 2.;
+        painter.text(
+            egui::pos2(split_x, rect.top() + max_height / 2.),
+            Align2::CENTER_CENTER,
+            format!(
+                "{} {:.0}%\n{} {:.0}%",
+                Hand::Left.description(),
+                self.hand_share(Hand::Left) * 100.,
+                Hand::Right.description(),
+                self.hand_share(Hand::Right) * 100.
+            ),
+            FontId::monospace(11.),
+            text_color,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_and_user_fingers_override_defaults() {
+        let layout = KeyboardLayout::from_json(
+            r#"{"name": "t", "rows": [{"keys": [
+                {"key": "KeyA"}, {"key": "KeyJ"}, {"key": "Return", "finger": "RightThumb"},
+                {"key": "Space"}
+            ]}]}"#,
+        )
+        .unwrap();
+        let overrides = FingerOverrides::from([(rdev::Key::Space, Finger::LeftThumb)]);
+        let fingers = FingerMap::new(&layout, Some(&overrides));
+
+        assert_eq!(fingers.get(rdev::Key::KeyA), Some(Finger::LeftPinky));
+        assert_eq!(fingers.get(rdev::Key::Return), Some(Finger::RightThumb));
+        assert_eq!(fingers.get(rdev::Key::Space), Some(Finger::LeftThumb));
+        assert_eq!(
+            restore_overrides(persisted_overrides(&overrides)),
+            overrides
+        );
+    }
+
+    #[test]
+    fn loads_split_presses_by_finger_and_hand() {
+        let layout = KeyboardLayout::from_json(
+            r#"{"name": "t", "rows": [{"keys": [{"key": "KeyA"}, {"key": "KeyJ"}]}]}"#,
+        )
+        .unwrap();
+        let mut map = PressTimesMap::new();
+        let now = std::time::SystemTime::now();
+        for key in [
+            rdev::Key::KeyA,
+            rdev::Key::KeyJ,
+            rdev::Key::KeyJ,
+            rdev::Key::KeyK,
+        ] {
+            map.key_press(key, now);
+            map.key_release(key, now);
+        }
+
+        let loads = FingerMap::new(&layout, None).loads(&map);
+        assert_eq!(loads.total(), 3);
+        assert!((loads.share(Finger::RightIndex) - 2. / 3.).abs() < 1e-6);
+        assert!((loads.hand_share(Hand::Left) - 1. / 3.).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,376 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::SystemTime,
+};
+
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Timelike};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    mouse::MouseInput,
+    press_time_map::{key_from_id, key_to_id},
+};
+
+/// Width of a history bucket, older buckets are merged into wider ones
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Deserialize, Serialize)]
+pub enum Granularity {
+    Hour,
+    Day,
+    Week,
+    Month,
+}
+
+impl Granularity {
+    /// Start of the bucket of this width containing `time`
+    fn bucket_start(&self, time: NaiveDateTime) -> NaiveDateTime {
+        let date = time.date();
+        match self {
+            Granularity::Hour => {
+                date.and_time(NaiveTime::MIN) + TimeDelta::hours(time.hour().into())
+            }
+            Granularity::Day => date.and_time(NaiveTime::MIN),
+            Granularity::Week => {
+                let monday = date - TimeDelta::days(date.weekday().num_days_from_monday().into());
+                monday.and_time(NaiveTime::MIN)
+            }
+            Granularity::Month => date.with_day(1).unwrap_or(date).and_time(NaiveTime::MIN),
+        }
+    }
+}
+
+// (width, days after which a bucket is merged, wider bucket it is merged into)
+const ROLL_UPS: [(Granularity, i64, Granularity); 3] = [
+    (Granularity::Hour, 7, Granularity::Day),
+    (Granularity::Day, 8 * 7, Granularity::Week),
+    (Granularity::Week, 365, Granularity::Month),
+];
+
+/// Counts collected during one bucket
+#[derive(Clone, Default)]
+pub struct Tally {
+    pub keys: HashMap<rdev::Key, u32>,
+    pub repeats: HashMap<rdev::Key, u32>,
+    pub chars: HashMap<String, u32>,
+    pub mouse: HashMap<MouseInput, u32>,
+    /// presses deleted right after being typed, by the key that typed them
+    pub errors: HashMap<rdev::Key, u32>,
+    /// typing time, pauses between presses longer than `speed::IDLE_GAP` left out
+    pub active_ms: u64,
+}
+
+impl Tally {
+    fn merge(&mut self, other: &Tally) {
+        for (&key, &count) in &other.keys {
+            *self.keys.entry(key).or_insert(0) += count;
+        }
+        for (&key, &count) in &other.repeats {
+            *self.repeats.entry(key).or_insert(0) += count;
+        }
+        for (name, &count) in &other.chars {
+            *self.chars.entry(name.clone()).or_insert(0) += count;
+        }
+        for (&input, &count) in &other.mouse {
+            *self.mouse.entry(input).or_insert(0) += count;
+        }
+        for (&key, &count) in &other.errors {
+            *self.errors.entry(key).or_insert(0) += count;
+        }
+        self.active_ms += other.active_ms;
+    }
+}
+
+/// Counts bucketed by the local time they were recorded at
+#[derive(Default)]
+pub struct History {
+    buckets: BTreeMap<(NaiveDateTime, Granularity), Tally>,
+}
+
+impl History {
+    /// Tally of the hour `time` falls in
+    pub fn at(&mut self, time: SystemTime) -> &mut Tally {
+        let time = DateTime::<Local>::from(time).naive_local();
+        let bucket = (Granularity::Hour.bucket_start(time), Granularity::Hour);
+        // old buckets only need merging when a new hour starts
+        if !self.buckets.contains_key(&bucket) {
+            self.roll_up(time);
+        }
+        self.buckets.entry(bucket).or_default()
+    }
+
+    pub fn roll_up(&mut self, now: NaiveDateTime) {
+        for (granularity, days, wider) in ROLL_UPS {
+            let expired: Vec<_> = self
+                .buckets
+                .keys()
+                .filter(|(start, width)| {
+                    *width == granularity && now - *start > TimeDelta::days(days)
+                })
+                .copied()
+                .collect();
+            for bucket in expired {
+                if let Some(tally) = self.buckets.remove(&bucket) {
+                    self.buckets
+                        .entry((wider.bucket_start(bucket.0), wider))
+                        .or_default()
+                        .merge(&tally);
+                }
+            }
+        }
+    }
+
+    /// Counts of the buckets starting in `[from, to)`, rolled up buckets are
+    /// counted whole by their start
+    pub fn tally(&self, from: NaiveDateTime, to: NaiveDateTime) -> Tally {
+        let mut total = Tally::default();
+        for ((start, _), tally) in &self.buckets {
+            if *start >= from && *start < to {
+                total.merge(tally);
+            }
+        }
+        total
+    }
+
+    /// Tally of each day in `[from, to]`
+    pub fn daily(&self, from: NaiveDate, to: NaiveDate) -> Vec<(NaiveDate, Tally)> {
+        from.iter_days()
+            .take_while(|day| *day <= to)
+            .map(|day| {
+                let start = day.and_time(NaiveTime::MIN);
+                (day, self.tally(start, start + TimeDelta::days(1)))
+            })
+            .collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.buckets.clear();
+    }
+
+    pub fn persisted_buckets(&self) -> Vec<PersistedBucket> {
+        self.buckets
+            .iter()
+            .map(|(&(start, granularity), tally)| PersistedBucket {
+                start,
+                granularity,
+                keys: persisted_keys(&tally.keys),
+                repeats: persisted_keys(&tally.repeats),
+                chars: {
+                    let mut chars: Vec<_> = tally
+                        .chars
+                        .iter()
+                        .map(|(name, &count)| (name.clone(), count))
+                        .collect();
+                    chars.sort();
+                    chars
+                },
+                mouse: {
+                    let mut mouse: Vec<_> = tally
+                        .mouse
+                        .iter()
+                        .map(|(&input, &count)| (input, count))
+                        .collect();
+                    mouse.sort();
+                    mouse
+                },
+                errors: persisted_keys(&tally.errors),
+                active_ms: tally.active_ms,
+            })
+            .collect()
+    }
+
+    pub fn from_persisted_buckets(buckets: Vec<PersistedBucket>) -> Self {
+        let mut history = Self::default();
+        for bucket in buckets {
+            let tally = Tally {
+                keys: restored_keys(bucket.keys),
+                repeats: restored_keys(bucket.repeats),
+                chars: bucket.chars.into_iter().collect(),
+                mouse: bucket.mouse.into_iter().collect(),
+                errors: restored_keys(bucket.errors),
+                active_ms: bucket.active_ms,
+            };
+            history
+                .buckets
+                .entry((bucket.start, bucket.granularity))
+                .or_default()
+                .merge(&tally);
+        }
+        history
+    }
+
+    /// Adds the buckets of `other` to the ones with the same start and width
+    pub fn merge(&mut self, other: History) {
+        for (bucket, tally) in other.buckets {
+            self.buckets.entry(bucket).or_default().merge(&tally);
+        }
+    }
+
+    /// Puts counts recorded before history was kept into the bucket of `time`
+    pub fn seed(&mut self, time: NaiveDateTime, tally: &Tally) {
+        self.buckets
+            .entry((Granularity::Hour.bucket_start(time), Granularity::Hour))
+            .or_default()
+            .merge(tally);
+        self.roll_up(Local::now().naive_local());
+    }
+}
+
+fn persisted_keys(keys: &HashMap<rdev::Key, u32>) -> Vec<(String, u32)> {
+    let mut entries: Vec<_> = keys
+        .iter()
+        .map(|(&key, &count)| (key_to_id(key), count))
+        .collect();
+    entries.sort();
+    entries
+}
+
+fn restored_keys(entries: Vec<(String, u32)>) -> HashMap<rdev::Key, u32> {
+    entries
+        .into_iter()
+        .filter_map(|(id, count)| Some((key_from_id(&id)?, count)))
+        .collect()
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct PersistedBucket {
+    start: NaiveDateTime,
+    granularity: Granularity,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    keys: Vec<(String, u32)>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    repeats: Vec<(String, u32)>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    chars: Vec<(String, u32)>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    mouse: Vec<(MouseInput, u32)>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<(String, u32)>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    active_ms: u64,
+}
+
+impl PersistedBucket {
+    pub fn start(&self) -> NaiveDateTime {
+        self.start
+    }
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+/// Span of history the heatmap shows
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum HistoryRange {
+    #[default]
+    AllTime,
+    Today,
+    Last7Days,
+    Last30Days,
+    ThisMonth,
+    /// both days included
+    Custom(NaiveDate, NaiveDate),
+}
+
+impl HistoryRange {
+    pub const PRESETS: [HistoryRange; 5] = [
+        HistoryRange::AllTime,
+        HistoryRange::Today,
+        HistoryRange::Last7Days,
+        HistoryRange::Last30Days,
+        HistoryRange::ThisMonth,
+    ];
+
+    pub fn description(&self) -> String {
+        match self {
+            HistoryRange::AllTime => "All time".to_string(),
+            HistoryRange::Today => "Today".to_string(),
+            HistoryRange::Last7Days => "Last 7 days".to_string(),
+            HistoryRange::Last30Days => "Last 30 days".to_string(),
+            HistoryRange::ThisMonth => "This month".to_string(),
+            HistoryRange::Custom(from, to) => format!("{from} – {to}"),
+        }
+    }
+
+    /// `[from, to)` in local time, `None` for all time
+    pub fn bounds(&self, today: NaiveDate) -> Option<(NaiveDateTime, NaiveDateTime)> {
+        let tomorrow = (today + TimeDelta::days(1)).and_time(NaiveTime::MIN);
+        let days_back = |days: i64| (today - TimeDelta::days(days - 1)).and_time(NaiveTime::MIN);
+        match self {
+            HistoryRange::AllTime => None,
+            HistoryRange::Today => Some((days_back(1), tomorrow)),
+            HistoryRange::Last7Days => Some((days_back(7), tomorrow)),
+            HistoryRange::Last30Days => Some((days_back(30), tomorrow)),
+            HistoryRange::ThisMonth => Some((
+                Granularity::Month.bucket_start(today.and_time(NaiveTime::MIN)),
+                tomorrow,
+            )),
+            HistoryRange::Custom(from, to) => Some((
+                from.and_time(NaiveTime::MIN),
+                (*to + TimeDelta::days(1)).and_time(NaiveTime::MIN),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(date: &str, hour: u32) -> NaiveDateTime {
+        date.parse::<NaiveDate>()
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+    }
+
+    fn tally_of(key: rdev::Key, count: u32) -> Tally {
+        let mut tally = Tally::default();
+        tally.keys.insert(key, count);
+        tally
+    }
+
+    #[test]
+    fn old_buckets_roll_up_into_wider_ones() {
+        let mut history = History::default();
+        history.buckets.insert(
+            (time("2025-01-07", 9), Granularity::Hour),
+            tally_of(rdev::Key::KeyA, 2),
+        );
+        history.buckets.insert(
+            (time("2025-01-07", 15), Granularity::Hour),
+            tally_of(rdev::Key::KeyA, 3),
+        );
+        history.buckets.insert(
+            (time("2026-10-16", 9), Granularity::Hour),
+            tally_of(rdev::Key::KeyA, 1),
+        );
+        history.roll_up(time("2026-10-17", 12));
+
+        let granularities: Vec<_> = history.buckets.keys().map(|(_, width)| *width).collect();
+        assert_eq!(granularities, [Granularity::Month, Granularity::Hour]);
+
+        let january = history.tally(time("2025-01-01", 0), time("2025-02-01", 0));
+        assert_eq!(january.keys[&rdev::Key::KeyA], 5);
+    }
+
+    #[test]
+    fn ranges_include_whole_days() {
+        let today = "2026-10-17".parse::<NaiveDate>().unwrap();
+        let (from, to) = HistoryRange::Last7Days.bounds(today).unwrap();
+        assert_eq!(from, time("2026-10-11", 0));
+        assert_eq!(to, time("2026-10-18", 0));
+        assert!(HistoryRange::AllTime.bounds(today).is_none());
+    }
+
+    #[test]
+    fn buckets_survive_persistence() {
+        let mut history = History::default();
+        history.buckets.insert(
+            (time("2026-10-16", 9), Granularity::Hour),
+            tally_of(rdev::Key::KeyA, 4),
+        );
+        let restored = History::from_persisted_buckets(history.persisted_buckets());
+        let tally = restored.tally(time("2026-10-16", 0), time("2026-10-17", 0));
+        assert_eq!(tally.keys[&rdev::Key::KeyA], 4);
+    }
+}
@@ -1,49 +1,110 @@
-use egui::{Align2, Color32, RichText, Sense, Stroke, Ui, Vec2};
+use egui::{Align2, Color32, Id, Pos2, Rect, Response, RichText, Sense, Shape, Stroke, Ui, Vec2};
 
 use crate::color::{get_color, get_strike_color};
 
+// outline of the keys most often pressed after the hovered key
+const FOLLOW_COLOR: Color32 = Color32::from_rgb(235, 120, 20);
+
 /// Layout in a key box, shows how to display the key contents
 #[derive(Clone)]
 pub enum KeyTextsLayout {
     TopBottom((String, String)),
     Center1(String),
 }
+
+impl KeyTextsLayout {
+    /// Two legends are stacked, anything else is centered line by line
+    pub fn from_legends(legends: &[String]) -> Self {
+        match legends {
+            [top, bottom] => KeyTextsLayout::TopBottom((top.clone(), bottom.clone())),
+            legends => KeyTextsLayout::Center1(legends.join("\n")),
+        }
+    }
+}
+
 /// component of a key on a keyboard
 #[allow(dead_code)]
 pub struct KeyBox {
-    size: Vec2,
+    rect: Rect,
     rounding: f32,
     stroke_width: f32,
     layout: KeyTextsLayout,
-    key: rdev::Key,
+    key: Option<rdev::Key>,
     press_times: u32,
     hue: f32,
+    // clockwise, in degrees
+    rotation: f32,
+    // shown on hover instead of the press times
+    hover_text: Option<String>,
+    // rank among the keys most often pressed after the hovered key
+    follow_rank: Option<usize>,
+    // replaces the outline derived from the fill color
+    border_color: Option<Color32>,
 }
 
 impl KeyBox {
     pub fn new(
-        size: Vec2,
+        rect: Rect,
         texts: KeyTextsLayout,
-        key: rdev::Key,
+        key: Option<rdev::Key>,
         press_times: u32,
         hue: f32,
     ) -> KeyBox {
         Self {
-            size,
+            rect,
             rounding: 5.0,
             stroke_width: 2.0,
             layout: texts,
             key,
             press_times,
             hue,
+            rotation: 0.,
+            hover_text: None,
+            follow_rank: None,
+            border_color: None,
         }
     }
+
+    pub fn with_rotation(mut self, degrees: f32) -> Self {
+        self.rotation = degrees;
+        self
+    }
+
+    pub fn with_hover_text(mut self, text: Option<String>) -> Self {
+        self.hover_text = text;
+        self
+    }
+
+    pub fn with_follow_rank(mut self, rank: Option<usize>) -> Self {
+        self.follow_rank = rank;
+        self
+    }
+
+    pub fn with_border_color(mut self, color: Option<Color32>) -> Self {
+        self.border_color = color;
+        self
+    }
 }
 impl KeyBox {
-    pub fn ui(&mut self, ui: &mut Ui) {
-        let (rect, resp) = ui.allocate_exact_size(self.size, Sense::hover());
+    pub fn ui(&mut self, ui: &mut Ui, id: Id) -> Response {
+        let rect = self.rect;
+        let corners = rotated_corners(rect, self.rotation);
+        let resp = ui.interact(Rect::from_points(&corners), id, Sense::click());
         let filled_color = get_color(self.hue, self.press_times);
-        ui.painter().rect_filled(rect, self.rounding, filled_color);
+        let stroke = match (self.follow_rank, self.border_color) {
+            (Some(_), _) => Stroke::new(self.stroke_width * 1.5, FOLLOW_COLOR),
+            (None, Some(color)) => Stroke::new(self.stroke_width * 1.5, color),
+            (None, None) => Stroke::new(self.stroke_width, get_strike_color(filled_color)),
+        };
+        if self.rotation == 0. {
+            ui.painter().rect_filled(rect, self.rounding, filled_color);
+        } else {
+            ui.painter().add(Shape::convex_polygon(
+                corners.to_vec(),
+                filled_color,
+                Stroke::NONE,
+            ));
+        }
         match &self.layout {
             KeyTextsLayout::TopBottom(top_bottom) => {
                 ui.painter().text(
@@ -72,19 +133,54 @@ impl KeyBox {
             }
         }
 
-        ui.painter().rect_stroke(
-            rect,
-            self.rounding,
-            Stroke {
-                width: self.stroke_width,
-                color: get_strike_color(filled_color),
-            },
-            egui::StrokeKind::Inside,
-        );
+        if self.rotation == 0. {
+            ui.painter()
+                .rect_stroke(rect, self.rounding, stroke, egui::StrokeKind::Inside);
+        } else {
+            ui.painter()
+                .add(Shape::closed_line(corners.to_vec(), stroke));
+        }
+        if let Some(rank) = self.follow_rank {
+            ui.painter().text(
+                corners[0] + (rect.center() - corners[0]) * 0.25,
+                Align2::CENTER_CENTER,
+                format!("{}", rank + 1),
+                egui::FontId::monospace(11.),
+                FOLLOW_COLOR,
+            );
+        }
 
         let hover_ui = |ui: &mut Ui| {
-            ui.label(RichText::new(format!("{}", self.press_times)));
+            let text = match &self.hover_text {
+                Some(text) => text.clone(),
+                None => format!("{}", self.press_times),
+            };
+            ui.label(RichText::new(text));
         };
-        resp.on_hover_ui(hover_ui);
+        resp.on_hover_ui(hover_ui)
+    }
+}
+
+/// Corners of `rect` turned clockwise by `degrees` around its center
+pub fn rotated_corners(rect: Rect, degrees: f32) -> [Pos2; 4] {
+    let corners = [
+        rect.left_top(),
+        rect.right_top(),
+        rect.right_bottom(),
+        rect.left_bottom(),
+    ];
+    if degrees == 0. {
+        return corners;
     }
+
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let center = rect.center();
+    corners.map(|corner| {
+        let offset = corner - center;
+        center
+            + Vec2::new(
+                offset.x * cos - offset.y * sin,
+                offset.x * sin + offset.y * cos,
+            )
+    })
 }
@@ -1,18 +1,77 @@
-use std::sync::MutexGuard;
+use std::{collections::HashMap, sync::Arc};
 
 use crate::{
+    chord::Modifier,
+    finger::FingerMap,
     key_box::{KeyBox, KeyTextsLayout},
-    press_time_map::PressTimesMap,
+    layout::{self, KeyboardLayout, LayoutKey},
+    logical_layout::LogicalLayout,
+    mouse,
+    press_time_map::{self, PressTimesMap},
 };
 
-use egui::{Color32, Sense, Ui, Vec2};
+use egui::{Sense, Ui, Vec2};
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[derive(Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub enum KeyboardType {
     QwertyMac,
+    Compact60,
+    Compact65,
+    Compact75,
     Qwerty87,
+    Qwerty104,
+    Iso105,
+    Jis109,
+    Corne,
+    Sofle,
+    Ergodox,
+    Moonlander,
+    Planck,
+    Preonic,
+    /// layout file stored in the user layouts directory, by file stem
+    Custom(String),
+}
+
+/// What the heat of a key is computed from
+#[derive(Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum HeatmapMode {
+    /// presses of the physical key
+    #[default]
+    PhysicalKey,
+    /// characters the OS produced, placed on the key that types them under
+    /// the selected legends
+    ProducedCharacter,
+    /// mean time between press and release
+    HoldTime,
+    /// presses while the modifier was held down
+    WhileHeld(Modifier),
+    /// share of the presses deleted by Backspace right after
+    Errors,
+}
+
+impl HeatmapMode {
+    pub const ALL: [HeatmapMode; 8] = [
+        HeatmapMode::PhysicalKey,
+        HeatmapMode::ProducedCharacter,
+        HeatmapMode::HoldTime,
+        HeatmapMode::Errors,
+        HeatmapMode::WhileHeld(Modifier::Ctrl),
+        HeatmapMode::WhileHeld(Modifier::Alt),
+        HeatmapMode::WhileHeld(Modifier::Shift),
+        HeatmapMode::WhileHeld(Modifier::Meta),
+    ];
+
+    pub fn description(&self) -> String {
+        match self {
+            HeatmapMode::PhysicalKey => "Physical position".to_string(),
+            HeatmapMode::ProducedCharacter => "Produced character".to_string(),
+            HeatmapMode::HoldTime => "Average hold time".to_string(),
+            HeatmapMode::WhileHeld(modifier) => format!("While {} held", modifier.description()),
+            HeatmapMode::Errors => "Correction rate".to_string(),
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -22,646 +81,314 @@ pub struct KeyPreviewSpec {
 }
 
 impl KeyboardType {
-    pub fn description(&self) -> &'static str {
+    pub const BUILT_IN: [KeyboardType; 14] = [
+        KeyboardType::QwertyMac,
+        KeyboardType::Compact60,
+        KeyboardType::Compact65,
+        KeyboardType::Compact75,
+        KeyboardType::Qwerty87,
+        KeyboardType::Qwerty104,
+        KeyboardType::Iso105,
+        KeyboardType::Jis109,
+        KeyboardType::Corne,
+        KeyboardType::Sofle,
+        KeyboardType::Ergodox,
+        KeyboardType::Moonlander,
+        KeyboardType::Planck,
+        KeyboardType::Preonic,
+    ];
+
+    pub fn description(&self) -> String {
         match self {
-            KeyboardType::QwertyMac => "MacBook",
-            KeyboardType::Qwerty87 => "87 Keys",
+            KeyboardType::QwertyMac => "MacBook".to_string(),
+            KeyboardType::Compact60 => "60%".to_string(),
+            KeyboardType::Compact65 => "65%".to_string(),
+            KeyboardType::Compact75 => "75%".to_string(),
+            KeyboardType::Qwerty87 => "87 Keys".to_string(),
+            KeyboardType::Qwerty104 => "104 Keys".to_string(),
+            KeyboardType::Iso105 => "ISO 105 Keys".to_string(),
+            KeyboardType::Jis109 => "JIS 109 Keys".to_string(),
+            KeyboardType::Corne => "Corne".to_string(),
+            KeyboardType::Sofle => "Sofle".to_string(),
+            KeyboardType::Ergodox => "Ergodox".to_string(),
+            KeyboardType::Moonlander => "Moonlander".to_string(),
+            KeyboardType::Planck => "Planck".to_string(),
+            KeyboardType::Preonic => "Preonic".to_string(),
+            KeyboardType::Custom(name) => layout::layout_for(self)
+                .map(|layout| layout.name.clone())
+                .unwrap_or_else(|| name.clone()),
         }
     }
 
     pub fn window_size(&self) -> Vec2 {
+        let panel = mouse::panel_size();
+        layout::layout_for(self)
+            .map(|layout| {
+                let keyboard = layout.pixel_size();
+                Vec2::new(
+                    keyboard.x + mouse::PANEL_GAP + panel.x,
+                    keyboard.y.max(panel.y),
+                ) + Vec2::new(100.0, 120.0)
+            })
+            .unwrap_or(Vec2::new(900.0 + mouse::PANEL_GAP + panel.x, 450.0))
+    }
+
+    pub fn built_in_source(&self) -> Option<&'static str> {
         match self {
-            KeyboardType::QwertyMac => Vec2::new(900.0, 450.0),
-            KeyboardType::Qwerty87 => Vec2::new(1120.0, 450.0),
+            KeyboardType::QwertyMac => Some(include_str!("../layouts/qwerty_mac.json")),
+            KeyboardType::Compact60 => Some(include_str!("../layouts/compact_60.json")),
+            KeyboardType::Compact65 => Some(include_str!("../layouts/compact_65.json")),
+            KeyboardType::Compact75 => Some(include_str!("../layouts/compact_75.json")),
+            KeyboardType::Qwerty87 => Some(include_str!("../layouts/qwerty_87.json")),
+            KeyboardType::Qwerty104 => Some(include_str!("../layouts/qwerty_104.json")),
+            KeyboardType::Iso105 => Some(include_str!("../layouts/iso_105.json")),
+            KeyboardType::Jis109 => Some(include_str!("../layouts/jis_109.json")),
+            KeyboardType::Corne => Some(include_str!("../layouts/split_corne.json")),
+            KeyboardType::Sofle => Some(include_str!("../layouts/split_sofle.json")),
+            KeyboardType::Ergodox => Some(include_str!("../layouts/split_ergodox.json")),
+            KeyboardType::Moonlander => Some(include_str!("../layouts/split_moonlander.json")),
+            KeyboardType::Planck => Some(include_str!("../layouts/ortho_planck.json")),
+            KeyboardType::Preonic => Some(include_str!("../layouts/ortho_preonic.json")),
+            KeyboardType::Custom(_) => None,
         }
     }
 }
 
-pub fn key_preview_spec(keyboard_type: KeyboardType, key: rdev::Key) -> Option<KeyPreviewSpec> {
-    if let Some(label) = FN_KEYS_PAIRS
-        .iter()
-        .find_map(|(label, candidate)| (*candidate == key).then(|| single_label(*label, 1.0)))
-    {
-        return Some(label);
-    }
-
-    if let Some(label) = NUM_KEY_LINE_PAIRS
-        .iter()
-        .find_map(|(top, bottom, candidate)| {
-            (*candidate == key).then(|| double_label(*top, *bottom, 1.0))
-        })
-    {
-        return Some(label);
-    }
-
-    if let Some(label) = FIRST_ALPHA_LINE_PAIRS
-        .iter()
-        .chain(SECOND_ALPHA_LINE_PAIRS.iter())
-        .chain(THIRD_ALPHA_LINE_PAIRS.iter())
-        .find_map(|(label, candidate)| (*candidate == key).then(|| single_label(*label, 1.0)))
-    {
-        return Some(label);
+pub fn key_preview_spec(layout: &KeyboardLayout, key: rdev::Key) -> Option<KeyPreviewSpec> {
+    let layout_key = layout.find(key)?;
+    if layout_key.legends.is_empty() {
+        return None;
     }
 
-    Some(match keyboard_type {
-        KeyboardType::QwertyMac => match key {
-            rdev::Key::Escape => single_label("Esc", 1.4),
-            rdev::Key::Unknown(0) => single_label("Power", 1.2),
-            rdev::Key::Backspace => single_label("Back", 1.4),
-            rdev::Key::Tab => single_label("Tab", 1.4),
-            rdev::Key::LeftBracket => double_label("[", "{", 1.0),
-            rdev::Key::RightBracket => double_label("]", "}", 1.0),
-            rdev::Key::BackSlash => double_label("\\", "|", 1.0),
-            rdev::Key::CapsLock => single_label("Caps", 1.7),
-            rdev::Key::SemiColon => double_label(":", ";", 1.0),
-            rdev::Key::Quote => double_label("\"", "'", 1.0),
-            rdev::Key::Return => single_label("Enter", 1.85),
-            rdev::Key::ShiftLeft | rdev::Key::ShiftRight => single_label("Shift", 2.2),
-            rdev::Key::Comma => double_label("<", ",", 1.0),
-            rdev::Key::Dot => double_label(">", ".", 1.0),
-            rdev::Key::Slash => double_label("?", "/", 1.0),
-            rdev::Key::Function => single_label("Fn", 1.0),
-            rdev::Key::ControlLeft => single_label("Ctrl", 1.0),
-            rdev::Key::Alt | rdev::Key::AltGr => single_label("Opt", 1.0),
-            rdev::Key::MetaLeft | rdev::Key::MetaRight => single_label("Cmd", 1.2),
-            rdev::Key::Space => single_label("Space", 5.6),
-            rdev::Key::LeftArrow => single_label("←", 1.0),
-            rdev::Key::UpArrow => single_label("↑", 1.0),
-            rdev::Key::DownArrow => single_label("↓", 1.0),
-            rdev::Key::RightArrow => single_label("→", 1.0),
-            _ => return None,
-        },
-        KeyboardType::Qwerty87 => match key {
-            rdev::Key::Escape => single_label("Esc", 1.0),
-            rdev::Key::PrintScreen => single_label("PrtSc", 1.2),
-            rdev::Key::ScrollLock => single_label("ScrLk", 1.2),
-            rdev::Key::Pause => single_label("Pause", 1.2),
-            rdev::Key::Backspace => single_label("Back", 1.4),
-            rdev::Key::Insert => single_label("Ins", 1.0),
-            rdev::Key::Home => single_label("Home", 1.0),
-            rdev::Key::PageUp => single_label("PgUp", 1.0),
-            rdev::Key::Tab => single_label("Tab", 1.4),
-            rdev::Key::LeftBracket => double_label("[", "{", 1.0),
-            rdev::Key::RightBracket => double_label("]", "}", 1.0),
-            rdev::Key::BackSlash => double_label("\\", "|", 1.0),
-            rdev::Key::Delete => single_label("Del", 1.0),
-            rdev::Key::End => single_label("End", 1.0),
-            rdev::Key::PageDown => single_label("PgDn", 1.0),
-            rdev::Key::CapsLock => single_label("Caps", 1.7),
-            rdev::Key::SemiColon => double_label(":", ";", 1.0),
-            rdev::Key::Quote => double_label("\"", "'", 1.0),
-            rdev::Key::Return => single_label("Enter", 1.85),
-            rdev::Key::ShiftLeft | rdev::Key::ShiftRight => single_label("Shift", 2.2),
-            rdev::Key::Comma => double_label("<", ",", 1.0),
-            rdev::Key::Dot => double_label(">", ".", 1.0),
-            rdev::Key::Slash => double_label("?", "/", 1.0),
-            rdev::Key::ControlLeft | rdev::Key::ControlRight => single_label("Ctrl", 1.2),
-            rdev::Key::MetaLeft => single_label("Win", 1.2),
-            rdev::Key::Alt | rdev::Key::AltGr => single_label("Alt", 1.2),
-            rdev::Key::Space => single_label("Space", 6.0),
-            rdev::Key::Function => single_label("Fn", 1.0),
-            rdev::Key::Unknown(110) => single_label("Menu", 1.2),
-            rdev::Key::LeftArrow => single_label("←", 1.0),
-            rdev::Key::UpArrow => single_label("↑", 1.0),
-            rdev::Key::RightArrow => single_label("→", 1.0),
-            rdev::Key::DownArrow => single_label("↓", 1.0),
-            _ => return None,
-        },
+    Some(KeyPreviewSpec {
+        layout: layout_key.texts(),
+        width_units: layout_key.w,
     })
 }
 
-fn single_label(label: &str, width_units: f32) -> KeyPreviewSpec {
-    KeyPreviewSpec {
-        layout: KeyTextsLayout::Center1(label.to_string()),
-        width_units,
-    }
-}
-
-fn double_label(top: &str, bottom: &str, width_units: f32) -> KeyPreviewSpec {
-    KeyPreviewSpec {
-        layout: KeyTextsLayout::TopBottom((top.to_string(), bottom.to_string())),
-        width_units,
+/// Short name of a key for lists: the character it types under `logical`,
+/// else the unshifted legend when the layout has one
+pub fn key_label(
+    logical: LogicalLayout,
+    layout: Option<&KeyboardLayout>,
+    key: rdev::Key,
+) -> String {
+    if let Some((unshifted, shifted)) = logical.characters(key) {
+        // letters are labelled like their keycaps
+        return match shifted {
+            "" => unshifted.to_uppercase(),
+            _ => unshifted.to_string(),
+        };
     }
+    // the unshifted legend is listed last
+    layout
+        .and_then(|layout| layout.find(key))
+        .and_then(|layout_key| layout_key.legends.last())
+        .filter(|legend| !legend.is_empty())
+        .cloned()
+        .unwrap_or_else(|| press_time_map::key_to_id(key))
 }
 
-const FN_KEYS_PAIRS: [(&str, rdev::Key); 12] = [
-    ("F1", rdev::Key::F1),
-    ("F2", rdev::Key::F2),
-    ("F3", rdev::Key::F3),
-    ("F4", rdev::Key::F4),
-    ("F5", rdev::Key::F5),
-    ("F6", rdev::Key::F6),
-    ("F7", rdev::Key::F7),
-    ("F8", rdev::Key::F8),
-    ("F9", rdev::Key::F9),
-    ("F10", rdev::Key::F10),
-    ("F11", rdev::Key::F11),
-    ("F12", rdev::Key::F12),
-];
-const NUM_KEY_LINE_PAIRS: [(&str, &str, rdev::Key); 13] = [
-    ("`", "~", rdev::Key::BackQuote),
-    ("!", "1", rdev::Key::Num1),
-    ("@", "2", rdev::Key::Num2),
-    ("#", "3", rdev::Key::Num3),
-    ("$", "4", rdev::Key::Num4),
-    ("%", "5", rdev::Key::Num5),
-    ("^", "6", rdev::Key::Num6),
-    ("&", "7", rdev::Key::Num7),
-    ("*", "8", rdev::Key::Num8),
-    ("(", "9", rdev::Key::Num9),
-    (")", "0", rdev::Key::Num0),
-    ("-", "_", rdev::Key::Minus),
-    ("+", "=", rdev::Key::Equal),
-];
+// fewer presses than this give no meaningful correction rate
+const MIN_ERROR_PRESSES: u32 = 10;
 
-const FIRST_ALPHA_LINE_PAIRS: [(&str, rdev::Key); 10] = [
-    ("Q", rdev::Key::KeyQ),
-    ("W", rdev::Key::KeyW),
-    ("E", rdev::Key::KeyE),
-    ("R", rdev::Key::KeyR),
-    ("T", rdev::Key::KeyT),
-    ("Y", rdev::Key::KeyY),
-    ("U", rdev::Key::KeyU),
-    ("I", rdev::Key::KeyI),
-    ("O", rdev::Key::KeyO),
-    ("P", rdev::Key::KeyP),
-];
-
-const SECOND_ALPHA_LINE_PAIRS: [(&str, rdev::Key); 9] = [
-    ("A", rdev::Key::KeyA),
-    ("S", rdev::Key::KeyS),
-    ("D", rdev::Key::KeyD),
-    ("F", rdev::Key::KeyF),
-    ("G", rdev::Key::KeyG),
-    ("H", rdev::Key::KeyH),
-    ("J", rdev::Key::KeyJ),
-    ("K", rdev::Key::KeyK),
-    ("L", rdev::Key::KeyL),
-];
-
-const THIRD_ALPHA_LINE_PAIRS: [(&str, rdev::Key); 7] = [
-    ("Z", rdev::Key::KeyZ),
-    ("X", rdev::Key::KeyX),
-    ("C", rdev::Key::KeyC),
-    ("V", rdev::Key::KeyV),
-    ("B", rdev::Key::KeyB),
-    ("N", rdev::Key::KeyN),
-    ("M", rdev::Key::KeyM),
-];
-
-const SECTION_SPACE: f32 = 15.;
+// keys outlined after the hovered one
+const FOLLOWERS_SHOWN: usize = 3;
 
 pub struct Keyboard {
-    // different from key numbers (and OSs)
-    keyboard_type: KeyboardType,
+    // keys and their positions, `None` when the layout failed to load
+    layout: Option<Arc<KeyboardLayout>>,
     // [0, 1], the hue of the color
     hue: f32,
+    legends: LogicalLayout,
+    // legends replacing those of `legends`, by key
+    key_legends: HashMap<rdev::Key, Vec<String>>,
+    mode: HeatmapMode,
+    // add auto-repeat presses to the physical key counts
+    include_repeats: bool,
+    // tints key borders by the finger pressing them
+    fingers: Option<FingerMap>,
 }
 
 impl Keyboard {
-    pub fn new(keyboard_type: KeyboardType, hue: f32) -> Self {
-        Self { keyboard_type, hue }
-    }
-}
-impl Keyboard {
-    pub fn draw(&mut self, map: &MutexGuard<PressTimesMap>, ui: &mut Ui) {
-        match self.keyboard_type {
-            KeyboardType::QwertyMac => self.draw_mac_keyboard(map, ui),
-            KeyboardType::Qwerty87 => self.draw_87_keyboard(map, ui),
+    pub fn new(keyboard_type: &KeyboardType, hue: f32) -> Self {
+        Self {
+            layout: layout::layout_for(keyboard_type),
+            hue,
+            legends: LogicalLayout::default(),
+            key_legends: HashMap::new(),
+            mode: HeatmapMode::default(),
+            include_repeats: false,
+            fingers: None,
         }
     }
 
-    fn draw_mac_keyboard(&mut self, map: &MutexGuard<PressTimesMap>, ui: &mut Ui) {
-        let basic_size = Vec2 { x: 50., y: 50. };
-        // 1st line
-        ui.horizontal(|ui| {
-            self.draw_single_label_key(map, Vec2 { x: 70., y: 50. }, rdev::Key::Escape, "Esc", ui);
-
-            for key_pair in FN_KEYS_PAIRS.iter() {
-                self.draw_single_label_key(map, basic_size, key_pair.1, key_pair.0, ui)
-            }
-
-            self.draw_single_label_key(map, basic_size, rdev::Key::Unknown(0), "Power", ui);
-        });
-        ui.add_space(3.);
-
-        // 2nd line
-        ui.horizontal(|ui| {
-            for key_pair in NUM_KEY_LINE_PAIRS.iter() {
-                self.draw_double_labels_key(
-                    map, basic_size, key_pair.2, key_pair.0, key_pair.1, ui,
-                );
-            }
-
-            self.draw_single_label_key(
-                map,
-                Vec2 { x: 70., y: 50. },
-                rdev::Key::Backspace,
-                "Back",
-                ui,
-            );
-        });
-        ui.add_space(3.);
-
-        // tab line
-        ui.horizontal(|ui| {
-            self.draw_single_label_key(map, Vec2 { x: 70., y: 50. }, rdev::Key::Tab, "Tab", ui);
-
-            for key_pair in FIRST_ALPHA_LINE_PAIRS.iter() {
-                self.draw_single_label_key(map, basic_size, key_pair.1, key_pair.0, ui);
-            }
-
-            for key_pair in [
-                ("[", "{", rdev::Key::LeftBracket),
-                ("]", "}", rdev::Key::RightBracket),
-                ("\\", "|", rdev::Key::BackSlash),
-            ] {
-                self.draw_double_labels_key(
-                    map, basic_size, key_pair.2, key_pair.0, key_pair.1, ui,
-                );
-            }
-        });
-        ui.add_space(3.);
-
-        // caps lock line
-        ui.horizontal(|ui| {
-            self.draw_single_label_key(
-                map,
-                Vec2 { x: 85., y: 50. },
-                rdev::Key::CapsLock,
-                "Caps\nLock",
-                ui,
-            );
-
-            for key_pair in SECOND_ALPHA_LINE_PAIRS.iter() {
-                self.draw_single_label_key(map, basic_size, key_pair.1, key_pair.0, ui);
-            }
-
-            for key_pair in [
-                (":", ";", rdev::Key::SemiColon),
-                ("\"", "'", rdev::Key::Quote),
-            ] {
-                self.draw_double_labels_key(
-                    map, basic_size, key_pair.2, key_pair.0, key_pair.1, ui,
-                );
-            }
-
-            // enter
-            self.draw_single_label_key(
-                map,
-                Vec2 { x: 93., y: 50. },
-                rdev::Key::Return,
-                "Enter",
-                ui,
-            );
-        });
-        ui.add_space(3.);
-
-        // shift line
-        ui.horizontal(|ui| {
-            self.draw_single_label_key(
-                map,
-                Vec2 { x: 118., y: 50. },
-                rdev::Key::ShiftLeft,
-                "Shift",
-                ui,
-            );
-
-            for key_pair in THIRD_ALPHA_LINE_PAIRS.iter() {
-                self.draw_single_label_key(map, basic_size, key_pair.1, key_pair.0, ui)
-            }
-
-            for key_pair in [
-                ("<", ",", rdev::Key::Comma),
-                (">", ".", rdev::Key::Dot),
-                ("?", "/", rdev::Key::Slash),
-            ] {
-                self.draw_double_labels_key(
-                    map, basic_size, key_pair.2, key_pair.0, key_pair.1, ui,
-                );
-            }
-
-            // right shift
-            self.draw_single_label_key(
-                map,
-                Vec2 { x: 118., y: 50. },
-                rdev::Key::ShiftRight,
-                "Shift",
-                ui,
-            );
-        });
-        ui.add_space(3.);
-
-        // last line
-        ui.horizontal(|ui| {
-            self.draw_single_label_key(map, basic_size, rdev::Key::Function, "Fn", ui);
-            self.draw_single_label_key(map, basic_size, rdev::Key::ControlLeft, "Ctrl", ui);
-            self.draw_single_label_key(map, basic_size, rdev::Key::Alt, "Opt", ui);
-            self.draw_single_label_key(
-                map,
-                Vec2 { x: 61., y: 50. },
-                rdev::Key::MetaLeft,
-                "Cmd",
-                ui,
-            );
-            self.draw_single_label_key(map, Vec2 { x: 280., y: 50. }, rdev::Key::Space, " ", ui);
-            self.draw_single_label_key(
-                map,
-                Vec2 { x: 61., y: 50. },
-                rdev::Key::MetaRight,
-                "Cmd",
-                ui,
-            );
-            self.draw_single_label_key(map, basic_size, rdev::Key::AltGr, "Opt", ui);
-
-            let left_times = map.get_key_times(rdev::Key::LeftArrow);
-            let mut left_key = KeyBox::new(
-                Vec2 { x: 50., y: 24. },
-                KeyTextsLayout::Center1("←".to_string()),
-                rdev::Key::LeftArrow,
-                left_times,
-                self.hue,
-            );
-            ui.vertical(|ui| {
-                let (rect, _) = ui.allocate_exact_size(Vec2 { x: 50., y: 24. }, Sense::hover());
-                ui.painter().rect_filled(rect, 0., Color32::TRANSPARENT);
-                left_key.ui(ui);
-            });
-
-            let up_times = map.get_key_times(rdev::Key::UpArrow);
-            let mut up_key = KeyBox::new(
-                Vec2 { x: 50., y: 24. },
-                KeyTextsLayout::Center1("↑".to_string()),
-                rdev::Key::UpArrow,
-                up_times,
-                self.hue,
-            );
-            let down_times = map.get_key_times(rdev::Key::DownArrow);
-            let mut down_key = KeyBox::new(
-                Vec2 { x: 50., y: 24. },
-                KeyTextsLayout::Center1("↓".to_string()),
-                rdev::Key::DownArrow,
-                down_times,
-                self.hue,
-            );
-            ui.vertical(|ui| {
-                up_key.ui(ui);
-                down_key.ui(ui);
-            });
-
-            // ->
-            let right_times = map.get_key_times(rdev::Key::RightArrow);
-            let mut right_key = KeyBox::new(
-                Vec2 { x: 50., y: 24. },
-                KeyTextsLayout::Center1("→".to_string()),
-                rdev::Key::RightArrow,
-                right_times,
-                self.hue,
-            );
-            ui.vertical(|ui| {
-                let (rect, _) = ui.allocate_exact_size(Vec2 { x: 50., y: 25. }, Sense::hover());
-                ui.painter().rect_filled(rect, 0., Color32::TRANSPARENT);
-                right_key.ui(ui);
-            });
-        });
+    pub fn with_legends(mut self, legends: LogicalLayout) -> Self {
+        self.legends = legends;
+        self
     }
 
-    fn draw_87_keyboard(&mut self, map: &MutexGuard<PressTimesMap>, ui: &mut Ui) {
-        let basic_size = Vec2 { x: 50., y: 50. };
-        // 1st line
-        ui.horizontal(|ui| {
-            self.draw_single_label_key(map, basic_size, rdev::Key::Escape, "Esc", ui);
-
-            self.draw_empty_key(basic_size, ui);
-
-            for key_pair in FN_KEYS_PAIRS.iter() {
-                self.draw_single_label_key(map, basic_size, key_pair.1, key_pair.0, ui);
-                if key_pair.1 == rdev::Key::F4 || key_pair.1 == rdev::Key::F8 {
-                    ui.add_space(25.);
-                }
-            }
-
-            ui.add_space(SECTION_SPACE);
-
-            for key_pair in [
-                ("PrtSc", rdev::Key::PrintScreen),
-                ("ScrLk", rdev::Key::ScrollLock),
-                ("Pause", rdev::Key::Pause),
-            ] {
-                self.draw_single_label_key(map, basic_size, key_pair.1, key_pair.0, ui);
-            }
-        });
-        ui.add_space(3.);
-
-        // 2nd line
-        ui.horizontal(|ui| {
-            for key_pair in NUM_KEY_LINE_PAIRS.iter() {
-                self.draw_double_labels_key(
-                    map, basic_size, key_pair.2, key_pair.0, key_pair.1, ui,
-                );
-            }
-
-            self.draw_single_label_key(
-                map,
-                Vec2 { x: 100., y: 50. },
-                rdev::Key::Backspace,
-                "Back",
-                ui,
-            );
-
-            ui.add_space(SECTION_SPACE);
-
-            for key_pair in [
-                ("Ins", rdev::Key::Insert),
-                ("Home", rdev::Key::Home),
-                ("PgUp", rdev::Key::PageUp),
-            ] {
-                self.draw_single_label_key(map, basic_size, key_pair.1, key_pair.0, ui);
-            }
-        });
-        ui.add_space(3.);
-
-        // tab line
-        ui.horizontal(|ui| {
-            self.draw_single_label_key(map, Vec2 { x: 70., y: 50. }, rdev::Key::Tab, "Tab", ui);
-
-            for key_pair in FIRST_ALPHA_LINE_PAIRS.iter() {
-                self.draw_single_label_key(map, basic_size, key_pair.1, key_pair.0, ui);
-            }
-
-            for key_pair in [
-                ("[", "{", rdev::Key::LeftBracket),
-                ("]", "}", rdev::Key::RightBracket),
-            ] {
-                self.draw_double_labels_key(
-                    map, basic_size, key_pair.2, key_pair.0, key_pair.1, ui,
-                );
-            }
-
-            let key_pair = ("\\", "|", rdev::Key::BackSlash);
-            self.draw_double_labels_key(
-                map,
-                Vec2 { x: 80., y: 50. },
-                key_pair.2,
-                key_pair.0,
-                key_pair.1,
-                ui,
-            );
-
-            ui.add_space(SECTION_SPACE);
+    pub fn with_key_legends(mut self, key_legends: HashMap<rdev::Key, Vec<String>>) -> Self {
+        self.key_legends = key_legends;
+        self
+    }
 
-            for key_pair in [
-                ("Del", rdev::Key::Delete),
-                ("End", rdev::Key::End),
-                ("PgDn", rdev::Key::PageDown),
-            ] {
-                self.draw_single_label_key(map, basic_size, key_pair.1, key_pair.0, ui);
-            }
-        });
-        ui.add_space(3.);
+    pub fn with_mode(mut self, mode: HeatmapMode) -> Self {
+        self.mode = mode;
+        self
+    }
 
-        // caps lock line
-        ui.horizontal(|ui| {
-            self.draw_single_label_key(
-                map,
-                Vec2 { x: 85., y: 50. },
-                rdev::Key::CapsLock,
-                "Caps\nLock",
-                ui,
-            );
+    pub fn with_repeats(mut self, include_repeats: bool) -> Self {
+        self.include_repeats = include_repeats;
+        self
+    }
 
-            for key_pair in SECOND_ALPHA_LINE_PAIRS.iter() {
-                self.draw_single_label_key(map, basic_size, key_pair.1, key_pair.0, ui);
-            }
+    pub fn with_fingers(mut self, fingers: Option<FingerMap>) -> Self {
+        self.fingers = fingers;
+        self
+    }
 
-            for key_pair in [
-                (":", ";", rdev::Key::SemiColon),
-                ("\"", "'", rdev::Key::Quote),
-            ] {
-                self.draw_double_labels_key(
-                    map, basic_size, key_pair.2, key_pair.0, key_pair.1, ui,
-                );
+    /// Heat of a key, on the scale of press times the colors are tuned for
+    fn key_heat(&self, map: &PressTimesMap, key: rdev::Key) -> u32 {
+        match self.mode {
+            HeatmapMode::PhysicalKey if self.include_repeats => {
+                map.get_key_times(key) + map.get_repeat_times(key)
             }
+            HeatmapMode::PhysicalKey => map.get_key_times(key),
+            // keys that type no character keep their physical count
+            HeatmapMode::ProducedCharacter => match self.legends.characters(key) {
+                Some((unshifted, shifted)) => {
+                    map.get_char_times(unshifted) + map.get_char_times(shifted)
+                }
+                None => map.get_key_times(key),
+            },
+            // a 200 ms hold is as hot as 20 presses
+            HeatmapMode::HoldTime => map
+                .hold_stats(key)
+                .map(|stats| (stats.mean_ms() / 10) as u32)
+                .unwrap_or(0),
+            HeatmapMode::WhileHeld(modifier) => map.chords.get_held_times(modifier, key),
+            // one press in ten corrected is as hot as 20 presses
+            HeatmapMode::Errors => match map.get_key_times(key) + map.get_repeat_times(key) {
+                presses if presses < MIN_ERROR_PRESSES => 0,
+                presses => map.get_error_times(key) * 200 / presses,
+            },
+        }
+    }
 
-            // enter
-            self.draw_single_label_key(
-                map,
-                Vec2 { x: 123., y: 50. },
-                rdev::Key::Return,
-                "Enter",
-                ui,
-            );
-
-            ui.add_space(SECTION_SPACE);
-            for _ in 0..3 {
-                self.draw_empty_key(basic_size, ui);
+    fn hover_text(&self, map: &PressTimesMap, key: rdev::Key) -> Option<String> {
+        match self.mode {
+            HeatmapMode::PhysicalKey => match map.get_repeat_times(key) {
+                0 => None,
+                repeats => Some(format!(
+                    "{} presses\n{} auto-repeats{}",
+                    map.get_key_times(key),
+                    repeats,
+                    if self.include_repeats {
+                        ""
+                    } else {
+                        " (hidden)"
+                    }
+                )),
+            },
+            HeatmapMode::ProducedCharacter | HeatmapMode::WhileHeld(_) => None,
+            HeatmapMode::HoldTime => Some(match map.hold_stats(key) {
+                Some(stats) => format!(
+                    "{} holds\nmean {} ms\np95 {} ms\ntotal {:.1} s",
+                    stats.count,
+                    stats.mean_ms(),
+                    stats.p95_ms(),
+                    stats.total_ms as f64 / 1000.
+                ),
+                None => "no holds".to_string(),
+            }),
+            HeatmapMode::Errors => {
+                // characters typed by auto-repeat can be deleted too
+                let presses = map.get_key_times(key) + map.get_repeat_times(key);
+                let errors = map.get_error_times(key);
+                Some(match presses {
+                    0 => "no presses".to_string(),
+                    _ => format!(
+                        "{errors} corrected of {presses} presses ({:.1}%)",
+                        errors as f32 * 100. / presses as f32
+                    ),
+                })
             }
-        });
-        ui.add_space(3.);
+        }
+    }
 
-        // shift line
-        ui.horizontal(|ui| {
-            self.draw_single_label_key(
-                map,
-                Vec2 { x: 118., y: 50. },
-                rdev::Key::ShiftLeft,
-                "Shift",
-                ui,
+    fn key_texts(&self, layout_key: &LayoutKey) -> KeyTextsLayout {
+        layout_key
+            .key
+            .and_then(|key| {
+                self.key_legends
+                    .get(&key)
+                    .cloned()
+                    .or_else(|| self.legends.legends(key))
+            })
+            .map(|legends| KeyTextsLayout::from_legends(&legends))
+            .unwrap_or_else(|| layout_key.texts())
+    }
+}
+impl Keyboard {
+    /// Draws the keys and returns the key clicked this frame
+    pub fn draw(&mut self, map: &PressTimesMap, ui: &mut Ui) -> Option<rdev::Key> {
+        let Some(layout) = self.layout.clone() else {
+            ui.label("Keyboard layout could not be loaded");
+            return None;
+        };
+
+        // the key hovered last frame, whose most frequent followers are outlined
+        let hovered_id = ui.id().with("hovered_key");
+        let hovered = ui.data(|data| data.get_temp::<Option<rdev::Key>>(hovered_id));
+        let followers: Vec<_> = hovered
+            .flatten()
+            .map(|key| map.ngrams.followers(key, FOLLOWERS_SHOWN))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+
+        let mut hovered_now = None;
+        let mut clicked = None;
+        let (rect, _) = ui.allocate_exact_size(layout.pixel_size(), Sense::hover());
+        for (index, layout_key) in layout.keys.iter().enumerate() {
+            let times = layout_key
+                .key
+                .map(|key| self.key_heat(map, key))
+                .unwrap_or(0);
+            let follow_rank = layout_key
+                .key
+                .and_then(|key| followers.iter().position(|&follower| follower == key));
+            let mut key = KeyBox::new(
+                layout_key.rect(rect.min),
+                self.key_texts(layout_key),
+                layout_key.key,
+                times,
+                self.hue,
+            )
+            .with_rotation(layout_key.r)
+            .with_hover_text(layout_key.key.and_then(|key| self.hover_text(map, key)))
+            .with_follow_rank(follow_rank)
+            .with_border_color(
+                self.fingers
+                    .as_ref()
+                    .zip(layout_key.key)
+                    .and_then(|(fingers, key)| fingers.get(key))
+                    .map(|finger| finger.color()),
             );
-
-            for key_pair in THIRD_ALPHA_LINE_PAIRS.iter() {
-                self.draw_single_label_key(map, basic_size, key_pair.1, key_pair.0, ui)
+            let response = key.ui(ui, ui.id().with(("key", index)));
+            if response.hovered() {
+                hovered_now = layout_key.key;
             }
-
-            for key_pair in [
-                ("<", ",", rdev::Key::Comma),
-                (">", ".", rdev::Key::Dot),
-                ("?", "/", rdev::Key::Slash),
-            ] {
-                self.draw_double_labels_key(
-                    map, basic_size, key_pair.2, key_pair.0, key_pair.1, ui,
-                );
+            if response.clicked() {
+                clicked = layout_key.key;
             }
-
-            // right shift
-            self.draw_single_label_key(
-                map,
-                Vec2 { x: 148., y: 50. },
-                rdev::Key::ShiftRight,
-                "Shift",
-                ui,
-            );
-
-            ui.add_space(SECTION_SPACE);
-            self.draw_empty_key(basic_size, ui);
-            self.draw_single_label_key(map, basic_size, rdev::Key::UpArrow, "↑", ui);
-            self.draw_empty_key(basic_size, ui);
-        });
-        ui.add_space(3.);
-
-        // last line
-        ui.horizontal(|ui| {
-            let ctrl_size = Vec2 { x: 60., y: 50. };
-            self.draw_single_label_key(map, ctrl_size, rdev::Key::ControlLeft, "Ctrl", ui);
-
-            self.draw_single_label_key(map, ctrl_size, rdev::Key::MetaLeft, "Win", ui);
-            self.draw_single_label_key(map, ctrl_size, rdev::Key::Alt, "Alt", ui);
-
-            self.draw_single_label_key(map, Vec2 { x: 378., y: 50. }, rdev::Key::Space, " ", ui);
-
-            self.draw_single_label_key(map, ctrl_size, rdev::Key::AltGr, "Alt", ui);
-            self.draw_single_label_key(map, ctrl_size, rdev::Key::Function, "Fn", ui);
-            // no menu in rdev::Key
-            self.draw_single_label_key(map, ctrl_size, rdev::Key::Unknown(110), "Menu", ui);
-            self.draw_single_label_key(map, ctrl_size, rdev::Key::ControlRight, "Ctrl", ui);
-
-            ui.add_space(SECTION_SPACE);
-            self.draw_single_label_key(map, basic_size, rdev::Key::LeftArrow, "←", ui);
-            self.draw_single_label_key(map, basic_size, rdev::Key::UpArrow, "↑", ui);
-            self.draw_single_label_key(map, basic_size, rdev::Key::RightArrow, "→", ui);
-        });
-    }
-    fn draw_double_labels_key(
-        &mut self,
-        map: &MutexGuard<PressTimesMap>,
-        size: Vec2,
-        key: rdev::Key,
-        top_name: &str,
-        bottom_name: &str,
-        ui: &mut Ui,
-    ) {
-        let times = map.get_key_times(key);
-        let mut key = KeyBox::new(
-            size,
-            KeyTextsLayout::TopBottom((top_name.to_string(), bottom_name.to_string())),
-            key,
-            times,
-            self.hue,
-        );
-        key.ui(ui);
-    }
-
-    fn draw_single_label_key(
-        &mut self,
-        map: &MutexGuard<PressTimesMap>,
-        size: Vec2,
-        key: rdev::Key,
-        name: &str,
-        ui: &mut Ui,
-    ) {
-        let times = map.get_key_times(key);
-        let mut key = KeyBox::new(
-            size,
-            KeyTextsLayout::Center1(name.to_string()),
-            key,
-            times,
-            self.hue,
-        );
-        key.ui(ui);
-    }
-
-    fn draw_empty_key(&mut self, size: Vec2, ui: &mut Ui) {
-        let (rect, _) = ui.allocate_exact_size(size, Sense::hover());
-        ui.painter().rect_filled(rect, 0., Color32::TRANSPARENT);
+        }
+        if hovered.flatten() != hovered_now {
+            ui.ctx().request_repaint();
+        }
+        ui.data_mut(|data| data.insert_temp(hovered_id, hovered_now));
+        clicked
     }
 }
@@ -0,0 +1,474 @@
+use std::error::Error;
+
+use serde_json::{Map, Value};
+
+use egui::{Pos2, Rect};
+
+use crate::{
+    layout::{KeyboardLayout, LayoutKey, KEY_UNIT},
+    press_time_map,
+};
+
+/// Result of importing a keyboard-layout-editor.com JSON export
+pub struct KleImport {
+    pub layout: KeyboardLayout,
+    /// keys whose legends could not be bound to an `rdev::Key`, and keys
+    /// whose second rectangle was left out
+    pub unbound: Vec<String>,
+}
+
+/// Position state carried from one KLE key to the next
+#[derive(Clone, Copy)]
+struct Placement {
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32,
+    r: f32,
+    rx: f32,
+    ry: f32,
+    // legend alignment `a`, an index into `LEGEND_POSITIONS`
+    align: usize,
+    // the key has a second rectangle (`x2`, `y2`, `w2`, `h2`), e.g. ISO Enter
+    stepped: bool,
+}
+
+impl Default for Placement {
+    fn default() -> Self {
+        Self {
+            x: 0.,
+            y: 0.,
+            w: 1.,
+            h: 1.,
+            r: 0.,
+            rx: 0.,
+            ry: 0.,
+            align: 4,
+            stepped: false,
+        }
+    }
+}
+
+impl Placement {
+    /// Top-left corner of the unrotated key whose center sits where the cluster
+    /// rotation moves it, turning that key around its own center by the same
+    /// angle gives the KLE placement
+    fn origin_before_rotation(&self) -> (f32, f32) {
+        if self.r == 0. {
+            return (self.x, self.y);
+        }
+
+        let (sin, cos) = self.r.to_radians().sin_cos();
+        let dx = self.x + self.w / 2. - self.rx;
+        let dy = self.y + self.h / 2. - self.ry;
+        let center_x = self.rx + dx * cos - dy * sin;
+        let center_y = self.ry + dx * sin + dy * cos;
+        (center_x - self.w / 2., center_y - self.h / 2.)
+    }
+}
+
+/// Parses the raw data of a KLE layout: a list of rows, each made of legend
+/// strings and property objects (`x`, `y`, `w`, `h`, `r`, `rx`, `ry`, `a`)
+/// that apply to the keys after them. A second key rectangle is left out and
+/// the key is reported.
+pub fn parse(json: &str, fallback_name: &str) -> Result<KleImport, Box<dyn Error + Send + Sync>> {
+    let rows: Vec<Value> = serde_json::from_str(json)?;
+
+    let mut name = fallback_name.to_string();
+    let mut placed: Vec<(Vec<String>, Placement)> = Vec::new();
+    let mut current = Placement::default();
+
+    for row in &rows {
+        let items = match row {
+            Value::Array(items) => items,
+            Value::Object(metadata) => {
+                if let Some(Value::String(board_name)) = metadata.get("name") {
+                    name = board_name.clone();
+                }
+                continue;
+            }
+            _ => return Err(format!("unexpected KLE row `{row}`").into()),
+        };
+
+        for item in items {
+            match item {
+                Value::String(legend) => {
+                    placed.push((legends(legend, current.align), current));
+                    current.x += current.w;
+                    current.w = 1.;
+                    current.h = 1.;
+                    current.stepped = false;
+                }
+                Value::Object(properties) => apply_properties(&mut current, properties),
+                _ => return Err(format!("unexpected KLE key `{item}`").into()),
+            }
+        }
+
+        current.y += 1.;
+        current.x = current.rx;
+    }
+
+    let stepped: Vec<bool> = placed
+        .iter()
+        .map(|(_, placement)| placement.stepped)
+        .collect();
+    let mut keys: Vec<LayoutKey> = placed
+        .into_iter()
+        .map(|(legends, placement)| {
+            let (x, y) = placement.origin_before_rotation();
+            LayoutKey {
+                key: None,
+                legends,
+                x,
+                y,
+                w: placement.w,
+                h: placement.h,
+                r: placement.r,
+                finger: None,
+            }
+        })
+        .collect();
+
+    // rotated clusters may end up left of or above the origin
+    let bounds = keys
+        .iter()
+        .map(|key| key.bounds(Pos2::ZERO))
+        .reduce(|a, b| a.union(b))
+        .unwrap_or(Rect::ZERO);
+    let min_x = bounds.min.x / KEY_UNIT;
+    let min_y = bounds.min.y / KEY_UNIT;
+    let center_x = bounds.center().x / KEY_UNIT;
+
+    let mut unbound = Vec::new();
+    for (layout_key, stepped) in keys.iter_mut().zip(stepped) {
+        let left_side = layout_key.x + layout_key.w / 2. < center_x;
+        layout_key.key = key_for_legends(&layout_key.legends, layout_key.w, left_side);
+        let label = if layout_key.legends.is_empty() {
+            "(blank)".to_string()
+        } else {
+            layout_key.legends.join(" ")
+        };
+        let at = format!(
+            "at ({:.2}, {:.2})",
+            layout_key.x - min_x,
+            layout_key.y - min_y
+        );
+        if layout_key.key.is_none() {
+            unbound.push(format!("{label} {at}"));
+        }
+        if stepped {
+            unbound.push(format!("{label} {at}: drawn without its second rectangle"));
+        }
+        layout_key.x -= min_x;
+        layout_key.y -= min_y;
+    }
+
+    Ok(KleImport {
+        layout: KeyboardLayout { name, keys },
+        unbound,
+    })
+}
+
+fn apply_properties(current: &mut Placement, properties: &Map<String, Value>) {
+    let number = |name: &str| {
+        properties
+            .get(name)
+            .and_then(Value::as_f64)
+            .map(|v| v as f32)
+    };
+
+    if let Some(r) = number("r") {
+        current.r = r;
+    }
+    // a new rotation origin also moves the cursor there
+    if let Some(rx) = number("rx") {
+        current.rx = rx;
+        current.x = current.rx;
+        current.y = current.ry;
+    }
+    if let Some(ry) = number("ry") {
+        current.ry = ry;
+        current.x = current.rx;
+        current.y = current.ry;
+    }
+    current.x += number("x").unwrap_or(0.);
+    current.y += number("y").unwrap_or(0.);
+    if let Some(w) = number("w") {
+        current.w = w;
+    }
+    if let Some(h) = number("h") {
+        current.h = h;
+    }
+    if let Some(a) = number("a") {
+        current.align = (a as usize).min(LEGEND_POSITIONS.len() - 1);
+    }
+    // only the first rectangle of a stepped or ISO Enter key is drawn
+    if ["x2", "y2", "w2", "h2"]
+        .iter()
+        .any(|name| properties.contains_key(*name))
+    {
+        current.stepped = true;
+    }
+}
+
+// where the legends of a key go for each alignment `a`, in the order they are
+// written: 0-2 top, 3-5 middle, 6-8 bottom and 9-11 front, left to right,
+// -1 for a legend the alignment does not show
+const LEGEND_POSITIONS: [[i8; 12]; 8] = [
+    [0, 6, 2, 8, 9, 11, 3, 5, 1, 4, 7, 10],
+    [1, 7, -1, -1, 9, 11, 4, -1, -1, -1, -1, 10],
+    [3, -1, 5, -1, 9, 11, -1, -1, 4, -1, -1, 10],
+    [4, -1, -1, -1, 9, 11, -1, -1, -1, -1, -1, 10],
+    [0, 6, 2, 8, 10, -1, 3, 5, 1, 4, 7, -1],
+    [1, 7, -1, -1, 10, -1, 4, -1, -1, -1, -1, -1],
+    [3, -1, 5, -1, 10, -1, -1, -1, 4, -1, -1, -1],
+    [4, -1, -1, -1, 10, -1, -1, -1, -1, -1, -1, -1],
+];
+
+/// Legends of a key from top to bottom as its alignment places them, the
+/// first one of each row and at most two, front legends last
+fn legends(text: &str, align: usize) -> Vec<String> {
+    let mut by_position = vec![String::new(); 12];
+    for (legend, &position) in text.split('\n').zip(&LEGEND_POSITIONS[align]) {
+        if let Ok(position) = usize::try_from(position) {
+            by_position[position] = strip_markup(legend);
+        }
+    }
+    by_position
+        .chunks(3)
+        .filter_map(|row| row.iter().find(|legend| !legend.is_empty()).cloned())
+        .take(2)
+        .collect()
+}
+
+/// Drops the html tags KLE allows in legends, e.g. `<br>` or icon `<i>`s,
+/// while keeping a bare `<` legend
+fn strip_markup(legend: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    let mut chars = legend.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '<' if chars
+                .peek()
+                .is_some_and(|next| next.is_ascii_alphabetic() || *next == '/') =>
+            {
+                in_tag = true
+            }
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text.trim().to_string()
+}
+
+fn key_for_legends(legends: &[String], width: f32, left_side: bool) -> Option<rdev::Key> {
+    if legends.is_empty() {
+        // a wide blank key can only be the space bar
+        return (width >= 3.).then_some(rdev::Key::Space);
+    }
+
+    legends
+        .iter()
+        .find_map(|legend| key_for_legend(&legend.to_lowercase(), left_side))
+}
+
+fn key_for_legend(legend: &str, left_side: bool) -> Option<rdev::Key> {
+    let sided = |left: rdev::Key, right: rdev::Key| if left_side { left } else { right };
+
+    Some(match legend {
+        "a" => rdev::Key::KeyA,
+        "b" => rdev::Key::KeyB,
+        "c" => rdev::Key::KeyC,
+        "d" => rdev::Key::KeyD,
+        "e" => rdev::Key::KeyE,
+        "f" => rdev::Key::KeyF,
+        "g" => rdev::Key::KeyG,
+        "h" => rdev::Key::KeyH,
+        "i" => rdev::Key::KeyI,
+        "j" => rdev::Key::KeyJ,
+        "k" => rdev::Key::KeyK,
+        "l" => rdev::Key::KeyL,
+        "m" => rdev::Key::KeyM,
+        "n" => rdev::Key::KeyN,
+        "o" => rdev::Key::KeyO,
+        "p" => rdev::Key::KeyP,
+        "q" => rdev::Key::KeyQ,
+        "r" => rdev::Key::KeyR,
+        "s" => rdev::Key::KeyS,
+        "t" => rdev::Key::KeyT,
+        "u" => rdev::Key::KeyU,
+        "v" => rdev::Key::KeyV,
+        "w" => rdev::Key::KeyW,
+        "x" => rdev::Key::KeyX,
+        "y" => rdev::Key::KeyY,
+        "z" => rdev::Key::KeyZ,
+        "1" | "!" => rdev::Key::Num1,
+        "2" | "@" => rdev::Key::Num2,
+        "3" | "#" => rdev::Key::Num3,
+        "4" | "$" => rdev::Key::Num4,
+        "5" | "%" => rdev::Key::Num5,
+        "6" | "^" => rdev::Key::Num6,
+        "7" | "&" => rdev::Key::Num7,
+        "8" | "*" => rdev::Key::Num8,
+        "9" | "(" => rdev::Key::Num9,
+        "0" | ")" => rdev::Key::Num0,
+        "`" | "~" => rdev::Key::BackQuote,
+        "-" | "_" => rdev::Key::Minus,
+        "=" | "+" => rdev::Key::Equal,
+        "[" | "{" => rdev::Key::LeftBracket,
+        "]" | "}" => rdev::Key::RightBracket,
+        "\\" | "|" => rdev::Key::BackSlash,
+        ";" | ":" => rdev::Key::SemiColon,
+        "'" | "\"" => rdev::Key::Quote,
+        "," | "<" => rdev::Key::Comma,
+        "." | ">" => rdev::Key::Dot,
+        "/" | "?" => rdev::Key::Slash,
+        "f1" => rdev::Key::F1,
+        "f2" => rdev::Key::F2,
+        "f3" => rdev::Key::F3,
+        "f4" => rdev::Key::F4,
+        "f5" => rdev::Key::F5,
+        "f6" => rdev::Key::F6,
+        "f7" => rdev::Key::F7,
+        "f8" => rdev::Key::F8,
+        "f9" => rdev::Key::F9,
+        "f10" => rdev::Key::F10,
+        "f11" => rdev::Key::F11,
+        "f12" => rdev::Key::F12,
+        "esc" | "escape" => rdev::Key::Escape,
+        "tab" | "⇥" => rdev::Key::Tab,
+        "caps" | "caps lock" | "capslock" | "⇪" => rdev::Key::CapsLock,
+        "shift" | "⇧" => sided(rdev::Key::ShiftLeft, rdev::Key::ShiftRight),
+        "ctrl" | "control" | "⌃" => sided(rdev::Key::ControlLeft, rdev::Key::ControlRight),
+        "alt" | "opt" | "option" | "⌥" => sided(rdev::Key::Alt, rdev::Key::AltGr),
+        "altgr" | "alt gr" => rdev::Key::AltGr,
+        "win" | "super" | "meta" | "gui" | "cmd" | "command" | "⌘" => {
+            sided(rdev::Key::MetaLeft, rdev::Key::MetaRight)
+        }
+        "fn" => rdev::Key::Function,
+        "menu" | "app" | "apps" => rdev::Key::Unknown(110),
+        "enter" | "return" | "↵" | "⏎" => rdev::Key::Return,
+        "backspace" | "back" | "bksp" | "⌫" => rdev::Key::Backspace,
+        "space" | "spacebar" => rdev::Key::Space,
+        "ins" | "insert" => rdev::Key::Insert,
+        "del" | "delete" => rdev::Key::Delete,
+        "home" => rdev::Key::Home,
+        "end" => rdev::Key::End,
+        "pgup" | "pg up" | "page up" => rdev::Key::PageUp,
+        "pgdn" | "pg dn" | "page down" => rdev::Key::PageDown,
+        "prtsc" | "prtscn" | "print" | "print screen" => rdev::Key::PrintScreen,
+        "scrlk" | "scroll lock" => rdev::Key::ScrollLock,
+        "pause" | "break" | "pause break" => rdev::Key::Pause,
+        "num lock" | "numlock" | "num" => rdev::Key::NumLock,
+        "¥" | "yen" => press_time_map::KEY_YEN,
+        "ro" => press_time_map::KEY_RO,
+        "変換" | "henkan" => press_time_map::KEY_HENKAN,
+        "無変換" | "muhenkan" => press_time_map::KEY_MUHENKAN,
+        "かな" | "カナ" | "kana" => press_time_map::KEY_KANA,
+        "←" | "left" => rdev::Key::LeftArrow,
+        "↑" | "up" => rdev::Key::UpArrow,
+        "→" | "right" => rdev::Key::RightArrow,
+        "↓" | "down" => rdev::Key::DownArrow,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_widths_offsets_and_legends() {
+        let import = parse(
+            r#"[
+                {"name": "tiny"},
+                ["Esc", {"x": 1}, "F1"],
+                [{"w": 1.5}, "Tab", "Q", "!\n1"],
+                [{"w": 2.25}, "Shift", {"w": 2.75}, "Shift"],
+                [{"w": 6}, ""]
+            ]"#,
+            "fallback",
+        )
+        .unwrap();
+
+        let keys = &import.layout.keys;
+        assert_eq!(import.layout.name, "tiny");
+        assert_eq!(keys[1].x, 2.);
+        assert_eq!(keys[3].x, 1.5);
+        assert_eq!(keys[3].y, 1.);
+        assert_eq!(keys[4].key, Some(rdev::Key::Num1));
+        assert_eq!(keys[4].legends, vec!["!", "1"]);
+        assert_eq!(keys[5].key, Some(rdev::Key::ShiftLeft));
+        assert_eq!(keys[6].key, Some(rdev::Key::ShiftRight));
+        assert_eq!(keys[7].key, Some(rdev::Key::Space));
+        assert!(import.unbound.is_empty());
+    }
+
+    #[test]
+    fn rotation_origin_resets_cursor() {
+        let import = parse(
+            r#"[
+                ["A"],
+                [{"r": 90, "rx": 4, "ry": 0}, "B"]
+            ]"#,
+            "rotated",
+        )
+        .unwrap();
+
+        let b = &import.layout.keys[1];
+        // a quarter turn around (4, 0) moves the center of B from (4.5, 0.5) to (3.5, 0.5)
+        assert!((b.x - 3.).abs() < 1e-4);
+        assert!((b.y - 0.).abs() < 1e-4);
+        assert_eq!(b.r, 90.);
+    }
+
+    #[test]
+    fn keeps_angle_bracket_legends() {
+        assert_eq!(strip_markup("<"), "<");
+        assert_eq!(strip_markup("<i class='kb kb-Arrows-Up'></i>Up"), "Up");
+    }
+
+    #[test]
+    fn alignment_places_the_legends() {
+        let import = parse(
+            r#"[
+                [{"a": 7}, "A", {"a": 4}, "\n1\n!", {"a": 5}, "%\n5"]
+            ]"#,
+            "aligned",
+        )
+        .unwrap();
+
+        let keys = &import.layout.keys;
+        assert_eq!(keys[0].legends, vec!["A"]);
+        // the shifted legend sits top right, the primary one bottom left
+        assert_eq!(keys[1].legends, vec!["!", "1"]);
+        assert_eq!(keys[1].key, Some(rdev::Key::Num1));
+        assert_eq!(keys[2].legends, vec!["%", "5"]);
+    }
+
+    #[test]
+    fn reports_second_rectangles() {
+        let import = parse(
+            r#"[
+                [{"x": 13.75, "w": 1.25, "h": 2, "w2": 1.5, "h2": 1, "x2": -0.25}, "Enter", "A"]
+            ]"#,
+            "iso",
+        )
+        .unwrap();
+
+        let keys = &import.layout.keys;
+        assert_eq!((keys[0].w, keys[0].h), (1.25, 2.));
+        assert_eq!((keys[1].w, keys[1].h), (1., 1.));
+        assert_eq!(import.unbound.len(), 1);
+        assert!(import.unbound[0].starts_with("Enter"));
+    }
+
+    #[test]
+    fn reports_keys_without_binding() {
+        let import = parse(r#"[["Hyper", "A", ""]]"#, "unbound").unwrap();
+        assert_eq!(import.unbound.len(), 2);
+        assert!(import.unbound[0].starts_with("Hyper"));
+    }
+}
@@ -0,0 +1,334 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use egui::{Pos2, Rect, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app,
+    finger::Finger,
+    key_box::{rotated_corners, KeyTextsLayout},
+    keyboard::KeyboardType,
+    press_time_map,
+};
+
+/// distance between the origins of two neighbouring 1u keys, in pixels
+pub const KEY_UNIT: f32 = 56.;
+/// blank space left between two neighbouring keys, in pixels
+pub const KEY_GAP: f32 = 6.;
+
+const USER_LAYOUTS_DIR: &str = "layouts";
+const LAYOUT_EXTENSION: &str = "json";
+
+static LAYOUT_CACHE: OnceLock<Mutex<HashMap<KeyboardType, Option<Arc<KeyboardLayout>>>>> =
+    OnceLock::new();
+
+/// On-disk description of a keyboard, grouped into rows.
+///
+/// Keys are placed left to right: a key without `x` starts where the previous key
+/// of the same row ends, a row without `y` sits one unit below the previous row.
+#[derive(Serialize, Deserialize)]
+pub struct LayoutFile {
+    pub name: String,
+    pub rows: Vec<RowSpec>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RowSpec {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub y: Option<f32>,
+    pub keys: Vec<KeySpec>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct KeySpec {
+    /// key id as persisted in the state file, e.g. `KeyA` or `Unknown:110`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// one centered legend, or two stacked ones: the shifted legend first,
+    /// the unshifted one below it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub legends: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub y: Option<f32>,
+    #[serde(default = "one_unit", skip_serializing_if = "is_one_unit")]
+    pub w: f32,
+    #[serde(default = "one_unit", skip_serializing_if = "is_one_unit")]
+    pub h: f32,
+    /// clockwise rotation around the key center, in degrees
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub r: f32,
+    /// finger pressing the key when it differs from the standard assignment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finger: Option<Finger>,
+}
+
+fn is_zero(value: &f32) -> bool {
+    *value == 0.
+}
+
+fn one_unit() -> f32 {
+    1.
+}
+
+fn is_one_unit(value: &f32) -> bool {
+    *value == 1.
+}
+
+/// A key placed on the keyboard, position and size are in key units
+#[derive(Clone)]
+pub struct LayoutKey {
+    pub key: Option<rdev::Key>,
+    pub legends: Vec<String>,
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+    pub r: f32,
+    pub finger: Option<Finger>,
+}
+
+impl LayoutKey {
+    pub fn texts(&self) -> KeyTextsLayout {
+        KeyTextsLayout::from_legends(&self.legends)
+    }
+
+    /// Rect of the key before its rotation is applied
+    pub fn rect(&self, origin: Pos2) -> Rect {
+        Rect::from_min_size(
+            origin + Vec2::new(self.x, self.y) * KEY_UNIT,
+            Vec2::new(self.w, self.h) * KEY_UNIT - Vec2::splat(KEY_GAP),
+        )
+    }
+
+    /// Axis aligned rect covering the rotated key
+    pub fn bounds(&self, origin: Pos2) -> Rect {
+        Rect::from_points(&rotated_corners(self.rect(origin), self.r))
+    }
+}
+
+/// Flattened keyboard layout ready to be drawn
+pub struct KeyboardLayout {
+    pub name: String,
+    pub keys: Vec<LayoutKey>,
+}
+
+impl KeyboardLayout {
+    pub fn from_json(json: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let file: LayoutFile = serde_json::from_str(json)?;
+        Self::from_file(file)
+    }
+
+    pub fn from_file(file: LayoutFile) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let mut keys = Vec::new();
+        let mut row_y = 0.;
+
+        for (row_index, row) in file.rows.into_iter().enumerate() {
+            if let Some(y) = row.y {
+                row_y = y;
+            } else if row_index > 0 {
+                row_y += 1.;
+            }
+
+            let mut cursor_x = 0.;
+            for spec in row.keys {
+                let key = match spec.key.as_deref() {
+                    Some(id) => Some(
+                        press_time_map::key_from_id(id)
+                            .ok_or_else(|| format!("unknown key id `{id}`"))?,
+                    ),
+                    None => None,
+                };
+                let x = spec.x.unwrap_or(cursor_x);
+                cursor_x = x + spec.w;
+                keys.push(LayoutKey {
+                    key,
+                    legends: spec.legends,
+                    x,
+                    y: spec.y.unwrap_or(row_y),
+                    w: spec.w,
+                    h: spec.h,
+                    r: spec.r,
+                    finger: spec.finger,
+                });
+            }
+        }
+
+        Ok(Self {
+            name: file.name,
+            keys,
+        })
+    }
+
+    /// Converts back to the on-disk format, each key keeps its absolute position
+    pub fn to_file(&self) -> LayoutFile {
+        let mut rows: Vec<RowSpec> = Vec::new();
+        for layout_key in &self.keys {
+            let spec = KeySpec {
+                key: layout_key.key.map(press_time_map::key_to_id),
+                legends: layout_key.legends.clone(),
+                x: Some(layout_key.x),
+                y: None,
+                w: layout_key.w,
+                h: layout_key.h,
+                r: layout_key.r,
+                finger: layout_key.finger,
+            };
+            match rows.last_mut() {
+                Some(row) if row.y == Some(layout_key.y) => row.keys.push(spec),
+                _ => rows.push(RowSpec {
+                    y: Some(layout_key.y),
+                    keys: vec![spec],
+                }),
+            }
+        }
+
+        LayoutFile {
+            name: self.name.clone(),
+            rows,
+        }
+    }
+
+    /// Size of the area covered by keys, in pixels
+    pub fn pixel_size(&self) -> Vec2 {
+        self.keys.iter().fold(Vec2::ZERO, |size, key| {
+            size.max(key.bounds(Pos2::ZERO).max.to_vec2())
+        })
+    }
+
+    pub fn find(&self, key: rdev::Key) -> Option<&LayoutKey> {
+        self.keys
+            .iter()
+            .find(|layout_key| layout_key.key == Some(key))
+    }
+}
+
+/// Returns the layout of a keyboard type, built-in layouts are compiled in and
+/// user layouts are read from the layouts directory the first time they are used.
+pub fn layout_for(keyboard_type: &KeyboardType) -> Option<Arc<KeyboardLayout>> {
+    let mut cache = LAYOUT_CACHE
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap();
+    if let Some(layout) = cache.get(keyboard_type) {
+        return layout.clone();
+    }
+
+    let layout = match load_layout(keyboard_type) {
+        Ok(layout) => Some(Arc::new(layout)),
+        Err(err) => {
+            eprintln!("failed to load keyboard layout: {err}");
+            None
+        }
+    };
+    cache.insert(keyboard_type.clone(), layout.clone());
+    layout
+}
+
+/// Drops cached user layouts so edited files are picked up again
+pub fn forget_user_layouts() {
+    if let Some(cache) = LAYOUT_CACHE.get() {
+        cache
+            .lock()
+            .unwrap()
+            .retain(|keyboard_type, _| !matches!(keyboard_type, KeyboardType::Custom(_)));
+    }
+}
+
+fn load_layout(
+    keyboard_type: &KeyboardType,
+) -> Result<KeyboardLayout, Box<dyn Error + Send + Sync>> {
+    match keyboard_type {
+        KeyboardType::Custom(name) => {
+            KeyboardLayout::from_json(&fs::read_to_string(user_layout_path(name))?)
+        }
+        built_in => KeyboardLayout::from_json(built_in.built_in_source().unwrap_or_default()),
+    }
+}
+
+pub fn user_layouts_dir() -> PathBuf {
+    app::app_data_dir().join(USER_LAYOUTS_DIR)
+}
+
+pub fn user_layout_path(name: &str) -> PathBuf {
+    user_layouts_dir().join(format!("{name}.{LAYOUT_EXTENSION}"))
+}
+
+/// Names of the layout files found in the user layouts directory, sorted
+pub fn user_layout_names() -> Vec<String> {
+    let Ok(entries) = fs::read_dir(user_layouts_dir()) else {
+        return Vec::new();
+    };
+
+    let mut names: Vec<String> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == LAYOUT_EXTENSION)
+        })
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+        .collect();
+    names.sort();
+    names
+}
+
+pub fn save_user_layout(
+    name: &str,
+    layout: &KeyboardLayout,
+) -> Result<PathBuf, Box<dyn Error + Send + Sync>> {
+    let path = user_layout_path(name);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, serde_json::to_vec_pretty(&layout.to_file())?)?;
+    forget_user_layouts();
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_layouts_parse() {
+        for keyboard_type in KeyboardType::BUILT_IN {
+            let source = keyboard_type.built_in_source().unwrap();
+            let layout = KeyboardLayout::from_json(source).unwrap();
+            assert!(!layout.keys.is_empty());
+        }
+    }
+
+    #[test]
+    fn keys_follow_previous_key_in_row() {
+        let layout = KeyboardLayout::from_json(
+            r#"{"name": "t", "rows": [
+                {"keys": [{"key": "Tab", "w": 1.5}, {"key": "KeyQ"}]},
+                {"keys": [{"key": "KeyA", "x": 1.75}]}
+            ]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(layout.keys[1].x, 1.5);
+        assert_eq!(layout.keys[2].x, 1.75);
+        assert_eq!(layout.keys[2].y, 1.);
+    }
+
+    #[test]
+    fn round_trips_through_file_format() {
+        let source = KeyboardType::Qwerty87.built_in_source().unwrap();
+        let layout = KeyboardLayout::from_json(source).unwrap();
+        let json = serde_json::to_string(&layout.to_file()).unwrap();
+        let reparsed = KeyboardLayout::from_json(&json).unwrap();
+
+        assert_eq!(layout.keys.len(), reparsed.keys.len());
+        assert_eq!(layout.pixel_size(), reparsed.pixel_size());
+    }
+}
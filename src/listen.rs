@@ -1,17 +1,36 @@
-use std::sync::mpsc::SyncSender;
+use std::sync::mpsc::Sender;
 
 use rdev::listen as listen_event;
 
-pub fn listen_keyboard(sender: SyncSender<rdev::Event>) {
+use crate::chord::Modifiers;
+
+/// An input event and the modifiers held down when it happened
+pub struct InputEvent {
+    pub event: rdev::Event,
+    pub modifiers: Modifiers,
+}
+
+/// Forwards input events to `sender`. The channel is unbounded, so the OS
+/// hook never waits for the worker
+pub fn listen_keyboard(sender: Sender<InputEvent>) {
+    let mut modifiers = Modifiers::default();
     if let Err(error) = listen_event(move |event: rdev::Event| {
-        callback(event, sender.clone());
+        modifiers.update(&event.event_type);
+        callback(event, modifiers, sender.clone());
     }) {
         println!("Error: {:?}", error)
     }
 }
 
-fn callback(event: rdev::Event, sender: SyncSender<rdev::Event>) {
-    if let rdev::EventType::KeyPress(_) = event.event_type {
-        sender.send(event).unwrap();
+fn callback(event: rdev::Event, modifiers: Modifiers, sender: Sender<InputEvent>) {
+    match event.event_type {
+        rdev::EventType::KeyPress(_)
+        | rdev::EventType::KeyRelease(_)
+        | rdev::EventType::ButtonPress(_)
+        | rdev::EventType::Wheel { .. } => {
+            // the worker is gone once the app is closing, the event is dropped
+            let _ = sender.send(InputEvent { event, modifiers });
+        }
+        _ => {}
     }
 }
@@ -0,0 +1,330 @@
+use serde::{Deserialize, Serialize};
+
+/// Layout the OS uses to turn physical keys into characters, independent of
+/// the physical keyboard drawn on screen
+#[derive(Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum LogicalLayout {
+    #[default]
+    Qwerty,
+    Dvorak,
+    Colemak,
+    ColemakDh,
+    Workman,
+    Azerty,
+    Qwertz,
+}
+
+/// (key, unshifted, shifted) characters, an empty shifted character means the
+/// key is a letter and is labelled by its uppercase form
+type CharacterPairs = [(rdev::Key, &'static str, &'static str)];
+
+impl LogicalLayout {
+    pub const ALL: [LogicalLayout; 7] = [
+        LogicalLayout::Qwerty,
+        LogicalLayout::Dvorak,
+        LogicalLayout::Colemak,
+        LogicalLayout::ColemakDh,
+        LogicalLayout::Workman,
+        LogicalLayout::Azerty,
+        LogicalLayout::Qwertz,
+    ];
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            LogicalLayout::Qwerty => "QWERTY",
+            LogicalLayout::Dvorak => "Dvorak",
+            LogicalLayout::Colemak => "Colemak",
+            LogicalLayout::ColemakDh => "Colemak-DH",
+            LogicalLayout::Workman => "Workman",
+            LogicalLayout::Azerty => "AZERTY",
+            LogicalLayout::Qwertz => "QWERTZ",
+        }
+    }
+
+    fn remapped(&self) -> &'static CharacterPairs {
+        match self {
+            LogicalLayout::Qwerty => &[],
+            LogicalLayout::Dvorak => &DVORAK,
+            LogicalLayout::Colemak => &COLEMAK,
+            LogicalLayout::ColemakDh => &COLEMAK_DH,
+            LogicalLayout::Workman => &WORKMAN,
+            LogicalLayout::Azerty => &AZERTY,
+            LogicalLayout::Qwertz => &QWERTZ,
+        }
+    }
+
+    /// Unshifted and shifted characters produced by a physical key
+    pub fn characters(&self, key: rdev::Key) -> Option<(&'static str, &'static str)> {
+        self.remapped()
+            .iter()
+            .chain(QWERTY.iter())
+            .find(|(candidate, _, _)| *candidate == key)
+            .map(|&(_, unshifted, shifted)| (unshifted, shifted))
+    }
+
+    /// Physical keys that produce a character under this layout
+    pub fn keys(&self) -> impl Iterator<Item = rdev::Key> {
+        let extra = self
+            .remapped()
+            .iter()
+            .filter(|(key, _, _)| QWERTY.iter().all(|(base, _, _)| base != key));
+        QWERTY.iter().chain(extra).map(|&(key, _, _)| key)
+    }
+
+    /// Keycap legends for a physical key, `None` keeps the legends of the
+    /// physical layout file
+    pub fn legends(&self, key: rdev::Key) -> Option<Vec<String>> {
+        if *self == LogicalLayout::Qwerty {
+            return None;
+        }
+
+        let (unshifted, shifted) = self.characters(key)?;
+        Some(character_legends(unshifted, shifted))
+    }
+}
+
+/// A letter is labelled by its uppercase form, anything else by both characters
+pub fn character_legends(unshifted: &str, shifted: &str) -> Vec<String> {
+    match shifted {
+        "" => vec![unshifted.to_uppercase()],
+        _ => vec![shifted.to_string(), unshifted.to_string()],
+    }
+}
+
+const QWERTY: [(rdev::Key, &str, &str); 47] = [
+    (rdev::Key::BackQuote, "`", "~"),
+    (rdev::Key::Num1, "1", "!"),
+    (rdev::Key::Num2, "2", "@"),
+    (rdev::Key::Num3, "3", "#"),
+    (rdev::Key::Num4, "4", "$"),
+    (rdev::Key::Num5, "5", "%"),
+    (rdev::Key::Num6, "6", "^"),
+    (rdev::Key::Num7, "7", "&"),
+    (rdev::Key::Num8, "8", "*"),
+    (rdev::Key::Num9, "9", "("),
+    (rdev::Key::Num0, "0", ")"),
+    (rdev::Key::Minus, "-", "_"),
+    (rdev::Key::Equal, "=", "+"),
+    (rdev::Key::KeyQ, "q", ""),
+    (rdev::Key::KeyW, "w", ""),
+    (rdev::Key::KeyE, "e", ""),
+    (rdev::Key::KeyR, "r", ""),
+    (rdev::Key::KeyT, "t", ""),
+    (rdev::Key::KeyY, "y", ""),
+    (rdev::Key::KeyU, "u", ""),
+    (rdev::Key::KeyI, "i", ""),
+    (rdev::Key::KeyO, "o", ""),
+    (rdev::Key::KeyP, "p", ""),
+    (rdev::Key::LeftBracket, "[", "{"),
+    (rdev::Key::RightBracket, "]", "}"),
+    (rdev::Key::BackSlash, "\\", "|"),
+    (rdev::Key::KeyA, "a", ""),
+    (rdev::Key::KeyS, "s", ""),
+    (rdev::Key::KeyD, "d", ""),
+    (rdev::Key::KeyF, "f", ""),
+    (rdev::Key::KeyG, "g", ""),
+    (rdev::Key::KeyH, "h", ""),
+    (rdev::Key::KeyJ, "j", ""),
+    (rdev::Key::KeyK, "k", ""),
+    (rdev::Key::KeyL, "l", ""),
+    (rdev::Key::SemiColon, ";", ":"),
+    (rdev::Key::Quote, "'", "\""),
+    (rdev::Key::KeyZ, "z", ""),
+    (rdev::Key::KeyX, "x", ""),
+    (rdev::Key::KeyC, "c", ""),
+    (rdev::Key::KeyV, "v", ""),
+    (rdev::Key::KeyB, "b", ""),
+    (rdev::Key::KeyN, "n", ""),
+    (rdev::Key::KeyM, "m", ""),
+    (rdev::Key::Comma, ",", "<"),
+    (rdev::Key::Dot, ".", ">"),
+    (rdev::Key::Slash, "/", "?"),
+];
+
+const DVORAK: [(rdev::Key, &str, &str); 33] = [
+    (rdev::Key::Minus, "[", "{"),
+    (rdev::Key::Equal, "]", "}"),
+    (rdev::Key::KeyQ, "'", "\""),
+    (rdev::Key::KeyW, ",", "<"),
+    (rdev::Key::KeyE, ".", ">"),
+    (rdev::Key::KeyR, "p", ""),
+    (rdev::Key::KeyT, "y", ""),
+    (rdev::Key::KeyY, "f", ""),
+    (rdev::Key::KeyU, "g", ""),
+    (rdev::Key::KeyI, "c", ""),
+    (rdev::Key::KeyO, "r", ""),
+    (rdev::Key::KeyP, "l", ""),
+    (rdev::Key::LeftBracket, "/", "?"),
+    (rdev::Key::RightBracket, "=", "+"),
+    (rdev::Key::KeyS, "o", ""),
+    (rdev::Key::KeyD, "e", ""),
+    (rdev::Key::KeyF, "u", ""),
+    (rdev::Key::KeyG, "i", ""),
+    (rdev::Key::KeyH, "d", ""),
+    (rdev::Key::KeyJ, "h", ""),
+    (rdev::Key::KeyK, "t", ""),
+    (rdev::Key::KeyL, "n", ""),
+    (rdev::Key::SemiColon, "s", ""),
+    (rdev::Key::Quote, "-", "_"),
+    (rdev::Key::KeyZ, ";", ":"),
+    (rdev::Key::KeyX, "q", ""),
+    (rdev::Key::KeyC, "j", ""),
+    (rdev::Key::KeyV, "k", ""),
+    (rdev::Key::KeyB, "x", ""),
+    (rdev::Key::KeyN, "b", ""),
+    (rdev::Key::Comma, "w", ""),
+    (rdev::Key::Dot, "v", ""),
+    (rdev::Key::Slash, "z", ""),
+];
+
+const COLEMAK: [(rdev::Key, &str, &str); 17] = [
+    (rdev::Key::KeyE, "f", ""),
+    (rdev::Key::KeyR, "p", ""),
+    (rdev::Key::KeyT, "g", ""),
+    (rdev::Key::KeyY, "j", ""),
+    (rdev::Key::KeyU, "l", ""),
+    (rdev::Key::KeyI, "u", ""),
+    (rdev::Key::KeyO, "y", ""),
+    (rdev::Key::KeyP, ";", ":"),
+    (rdev::Key::KeyS, "r", ""),
+    (rdev::Key::KeyD, "s", ""),
+    (rdev::Key::KeyF, "t", ""),
+    (rdev::Key::KeyG, "d", ""),
+    (rdev::Key::KeyJ, "n", ""),
+    (rdev::Key::KeyK, "e", ""),
+    (rdev::Key::KeyL, "i", ""),
+    (rdev::Key::SemiColon, "o", ""),
+    (rdev::Key::KeyN, "k", ""),
+];
+
+const COLEMAK_DH: [(rdev::Key, &str, &str); 21] = [
+    (rdev::Key::KeyE, "f", ""),
+    (rdev::Key::KeyR, "p", ""),
+    (rdev::Key::KeyT, "b", ""),
+    (rdev::Key::KeyY, "j", ""),
+    (rdev::Key::KeyU, "l", ""),
+    (rdev::Key::KeyI, "u", ""),
+    (rdev::Key::KeyO, "y", ""),
+    (rdev::Key::KeyP, ";", ":"),
+    (rdev::Key::KeyS, "r", ""),
+    (rdev::Key::KeyD, "s", ""),
+    (rdev::Key::KeyF, "t", ""),
+    (rdev::Key::KeyG, "g", ""),
+    (rdev::Key::KeyH, "m", ""),
+    (rdev::Key::KeyJ, "n", ""),
+    (rdev::Key::KeyK, "e", ""),
+    (rdev::Key::KeyL, "i", ""),
+    (rdev::Key::SemiColon, "o", ""),
+    (rdev::Key::KeyV, "d", ""),
+    (rdev::Key::KeyB, "v", ""),
+    (rdev::Key::KeyN, "k", ""),
+    (rdev::Key::KeyM, "h", ""),
+];
+
+const WORKMAN: [(rdev::Key, &str, &str); 21] = [
+    (rdev::Key::KeyW, "d", ""),
+    (rdev::Key::KeyE, "r", ""),
+    (rdev::Key::KeyR, "w", ""),
+    (rdev::Key::KeyT, "b", ""),
+    (rdev::Key::KeyY, "j", ""),
+    (rdev::Key::KeyU, "f", ""),
+    (rdev::Key::KeyI, "u", ""),
+    (rdev::Key::KeyO, "p", ""),
+    (rdev::Key::KeyP, ";", ":"),
+    (rdev::Key::KeyD, "h", ""),
+    (rdev::Key::KeyF, "t", ""),
+    (rdev::Key::KeyH, "y", ""),
+    (rdev::Key::KeyJ, "n", ""),
+    (rdev::Key::KeyK, "e", ""),
+    (rdev::Key::KeyL, "o", ""),
+    (rdev::Key::SemiColon, "i", ""),
+    (rdev::Key::KeyC, "m", ""),
+    (rdev::Key::KeyV, "c", ""),
+    (rdev::Key::KeyB, "v", ""),
+    (rdev::Key::KeyN, "k", ""),
+    (rdev::Key::KeyM, "l", ""),
+];
+
+const AZERTY: [(rdev::Key, &str, &str); 27] = [
+    (rdev::Key::BackQuote, "²", ""),
+    (rdev::Key::Num1, "&", "1"),
+    (rdev::Key::Num2, "é", "2"),
+    (rdev::Key::Num3, "\"", "3"),
+    (rdev::Key::Num4, "'", "4"),
+    (rdev::Key::Num5, "(", "5"),
+    (rdev::Key::Num6, "-", "6"),
+    (rdev::Key::Num7, "è", "7"),
+    (rdev::Key::Num8, "_", "8"),
+    (rdev::Key::Num9, "ç", "9"),
+    (rdev::Key::Num0, "à", "0"),
+    (rdev::Key::Minus, ")", "°"),
+    (rdev::Key::Equal, "=", "+"),
+    (rdev::Key::KeyQ, "a", ""),
+    (rdev::Key::KeyW, "z", ""),
+    (rdev::Key::LeftBracket, "^", "¨"),
+    (rdev::Key::RightBracket, "$", "£"),
+    (rdev::Key::BackSlash, "*", "µ"),
+    (rdev::Key::KeyA, "q", ""),
+    (rdev::Key::SemiColon, "m", ""),
+    (rdev::Key::Quote, "ù", "%"),
+    (rdev::Key::KeyZ, "w", ""),
+    (rdev::Key::KeyM, ",", "?"),
+    (rdev::Key::Comma, ";", "."),
+    (rdev::Key::Dot, ":", "/"),
+    (rdev::Key::Slash, "!", "§"),
+    (rdev::Key::IntlBackslash, "<", ">"),
+];
+
+const QWERTZ: [(rdev::Key, &str, &str); 19] = [
+    (rdev::Key::BackQuote, "^", "°"),
+    (rdev::Key::Num2, "2", "\""),
+    (rdev::Key::Num3, "3", "§"),
+    (rdev::Key::Num6, "6", "&"),
+    (rdev::Key::Num7, "7", "/"),
+    (rdev::Key::Num8, "8", "("),
+    (rdev::Key::Num9, "9", ")"),
+    (rdev::Key::Num0, "0", "="),
+    (rdev::Key::Minus, "ß", "?"),
+    (rdev::Key::Equal, "´", "`"),
+    (rdev::Key::KeyY, "z", ""),
+    (rdev::Key::LeftBracket, "ü", ""),
+    (rdev::Key::RightBracket, "+", "*"),
+    (rdev::Key::SemiColon, "ö", ""),
+    (rdev::Key::Quote, "ä", ""),
+    (rdev::Key::BackSlash, "#", "'"),
+    (rdev::Key::KeyZ, "y", ""),
+    (rdev::Key::Slash, "-", "_"),
+    (rdev::Key::IntlBackslash, "<", ">"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn qwerty_keeps_physical_legends() {
+        assert!(LogicalLayout::Qwerty.legends(rdev::Key::KeyQ).is_none());
+        assert_eq!(
+            LogicalLayout::Qwerty.characters(rdev::Key::KeyQ),
+            Some(("q", ""))
+        );
+    }
+
+    #[test]
+    fn remapped_keys_fall_back_to_qwerty() {
+        assert_eq!(
+            LogicalLayout::Dvorak.legends(rdev::Key::KeyQ),
+            Some(vec!["\"".to_string(), "'".to_string()])
+        );
+        assert_eq!(
+            LogicalLayout::Colemak.legends(rdev::Key::KeyS),
+            Some(vec!["R".to_string()])
+        );
+        // Colemak leaves the bottom row letters in place
+        assert_eq!(
+            LogicalLayout::Colemak.characters(rdev::Key::KeyZ),
+            Some(("z", ""))
+        );
+        assert!(LogicalLayout::Azerty.characters(rdev::Key::Space).is_none());
+    }
+}
@@ -1,22 +1,37 @@
 #![windows_subsystem = "windows"]
 use eframe::egui::ViewportBuilder;
+mod activity;
 mod app;
+mod breaks;
+mod chord;
 mod color;
+mod event_store;
+mod finger;
+mod history;
 mod key_box;
 mod keyboard;
+mod kle;
+mod layout;
 mod listen;
+mod logical_layout;
+mod metrics;
+mod mouse;
+mod ngram;
 mod press_time_map;
+mod profile;
+mod simulate;
+mod speed;
+mod storage;
 mod tray;
 mod typing_log;
 mod window_visibility;
 
 fn main() -> eframe::Result {
-    let initial_keyboard_type = app::initial_keyboard_type();
-    let initial_window_size = initial_keyboard_type.window_size();
+    let initial_window_size = app::initial_window_size();
     let native_options = eframe::NativeOptions {
         viewport: ViewportBuilder::default()
             .with_inner_size(initial_window_size)
-            .with_min_inner_size(keyboard::KeyboardType::QwertyMac.window_size())
+            .with_min_inner_size(initial_window_size)
             .with_resizable(false),
         ..Default::default()
     };
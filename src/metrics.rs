@@ -0,0 +1,176 @@
+use egui::{Pos2, Ui};
+
+use crate::{finger::FingerMap, layout::KeyboardLayout};
+
+// horizontal distance between adjacent fingers, in key units, past which the
+// hand has to stretch sideways
+const STRETCH_DISTANCE: f32 = 1.5;
+// vertical distance, in key units, of a jump over the home row
+const ROW_JUMP_DISTANCE: f32 = 1.5;
+
+/// Layout quality counts over bigrams typed by the fingers, thumbs are left out
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct BigramMetrics {
+    /// bigrams whose keys both have a finger other than a thumb
+    pub total: u64,
+    /// two different keys pressed by the same finger
+    pub same_finger: u64,
+    /// adjacent fingers of one hand reaching two or more columns apart
+    pub lateral_stretches: u64,
+    /// different fingers of one hand jumping between the top and bottom rows
+    pub row_jumps: u64,
+    pub alternations: u64,
+    /// one hand moving from the pinky towards the index
+    pub inward_rolls: u64,
+    pub outward_rolls: u64,
+}
+
+impl BigramMetrics {
+    pub fn new(
+        layout: &KeyboardLayout,
+        fingers: &FingerMap,
+        bigrams: impl IntoIterator<Item = ([rdev::Key; 2], u32)>,
+    ) -> Self {
+        let placed = |key: rdev::Key| {
+            let finger = fingers.get(key).filter(|finger| finger.column() < 4)?;
+            let layout_key = layout.find(key)?;
+            let center = Pos2::new(
+                layout_key.x + layout_key.w / 2.,
+                layout_key.y + layout_key.h / 2.,
+            );
+            Some((finger, center))
+        };
+
+        let mut metrics = Self::default();
+        for ([first, second], times) in bigrams {
+            let (Some((first_finger, from)), Some((second_finger, to))) =
+                (placed(first), placed(second))
+            else {
+                continue;
+            };
+            let times = u64::from(times);
+            metrics.total += times;
+            if first_finger.hand() != second_finger.hand() {
+                metrics.alternations += times;
+                continue;
+            }
+            if first_finger == second_finger {
+                if first != second {
+                    metrics.same_finger += times;
+                }
+                continue;
+            }
+
+            if second_finger.column() > first_finger.column() {
+                metrics.inward_rolls += times;
+            } else {
+                metrics.outward_rolls += times;
+            }
+            let adjacent = first_finger.column().abs_diff(second_finger.column()) == 1;
+            if adjacent && (to.x - from.x).abs() > STRETCH_DISTANCE {
+                metrics.lateral_stretches += times;
+            }
+            if (to.y - from.y).abs() > ROW_JUMP_DISTANCE {
+                metrics.row_jumps += times;
+            }
+        }
+        metrics
+    }
+
+    /// Share of the counted bigrams in `[0, 1]`
+    pub fn rate(&self, count: u64) -> f32 {
+        match self.total {
+            0 => 0.,
+            total => count as f32 / total as f32,
+        }
+    }
+
+    /// (name, count, what it means) in the order they are listed
+    pub fn rows(&self) -> [(&'static str, u64, &'static str); 6] {
+        [
+            (
+                "Same finger",
+                self.same_finger,
+                "Two different keys typed with the same finger",
+            ),
+            (
+                "Lateral stretch",
+                self.lateral_stretches,
+                "Neighbouring fingers of one hand two or more columns apart",
+            ),
+            (
+                "Row jump",
+                self.row_jumps,
+                "One hand jumping between the top and bottom rows",
+            ),
+            (
+                "Alternation",
+                self.alternations,
+                "The other hand types the next key",
+            ),
+            (
+                "Inward roll",
+                self.inward_rolls,
+                "One hand rolling from the pinky towards the index",
+            ),
+            (
+                "Outward roll",
+                self.outward_rolls,
+                "One hand rolling from the index towards the pinky",
+            ),
+        ]
+    }
+
+    pub fn draw_table(&self, ui: &mut Ui, id: &str) {
+        egui::Grid::new(id).striped(true).show(ui, |ui| {
+            for (name, count, hint) in self.rows() {
+                ui.label(name).on_hover_text(hint);
+                ui.monospace(format!("{:5.1}%", self.rate(count) * 100.));
+                ui.label(count.to_string());
+                ui.end_row();
+            }
+        });
+        ui.label(format!("{} bigrams counted", self.total));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keyboard::KeyboardType;
+
+    #[test]
+    fn bigrams_are_classified_on_the_layout() {
+        let layout =
+            KeyboardLayout::from_json(KeyboardType::Qwerty87.built_in_source().unwrap()).unwrap();
+        let fingers = FingerMap::new(&layout, None);
+        let bigrams = [
+            // same finger, and a repeat that is not
+            ([rdev::Key::KeyE, rdev::Key::KeyD], 2),
+            ([rdev::Key::KeyE, rdev::Key::KeyE], 1),
+            // alternation
+            ([rdev::Key::KeyT, rdev::Key::KeyH], 3),
+            // inward roll that stretches sideways
+            ([rdev::Key::KeyE, rdev::Key::KeyT], 1),
+            // outward roll jumping from the bottom row to the top one
+            ([rdev::Key::KeyV, rdev::Key::KeyW], 1),
+            // thumbs are not counted
+            ([rdev::Key::Space, rdev::Key::KeyA], 5),
+        ];
+        let metrics = BigramMetrics::new(&layout, &fingers, bigrams);
+
+        assert_eq!(
+            metrics,
+            BigramMetrics {
+                total: 8,
+                same_finger: 2,
+                lateral_stretches: 1,
+                row_jumps: 1,
+                alternations: 3,
+                inward_rolls: 1,
+                outward_rolls: 1,
+            }
+        );
+        assert_eq!(metrics.rate(metrics.alternations), 3. / 8.);
+    }
+}
@@ -0,0 +1,116 @@
+use egui::{Rect, Sense, Ui, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    key_box::{KeyBox, KeyTextsLayout},
+    layout::{KEY_GAP, KEY_UNIT},
+    press_time_map::PressTimesMap,
+};
+
+/// Space between the keyboard and the mouse panel
+pub const PANEL_GAP: f32 = 30.;
+
+/// Mouse buttons and scroll directions, each wheel event counts as one notch
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Deserialize, Serialize)]
+pub enum MouseInput {
+    Left,
+    Right,
+    Middle,
+    Back,
+    Forward,
+    ScrollUp,
+    ScrollDown,
+    ScrollLeft,
+    ScrollRight,
+}
+
+impl MouseInput {
+    pub fn from_button(button: rdev::Button) -> Option<Self> {
+        match button {
+            rdev::Button::Left => Some(MouseInput::Left),
+            rdev::Button::Right => Some(MouseInput::Right),
+            rdev::Button::Middle => Some(MouseInput::Middle),
+            #[cfg(target_os = "windows")]
+            rdev::Button::Unknown(1) => Some(MouseInput::Back),
+            #[cfg(target_os = "windows")]
+            rdev::Button::Unknown(2) => Some(MouseInput::Forward),
+            #[cfg(all(not(target_os = "windows"), not(target_os = "macos")))]
+            rdev::Button::Unknown(8) => Some(MouseInput::Back),
+            #[cfg(all(not(target_os = "windows"), not(target_os = "macos")))]
+            rdev::Button::Unknown(9) => Some(MouseInput::Forward),
+            _ => None,
+        }
+    }
+
+    pub fn from_wheel(delta_x: i64, delta_y: i64) -> impl Iterator<Item = Self> {
+        let vertical = match delta_y {
+            0 => None,
+            dy if dy > 0 => Some(MouseInput::ScrollUp),
+            _ => Some(MouseInput::ScrollDown),
+        };
+        let horizontal = match delta_x {
+            0 => None,
+            dx if dx > 0 => Some(MouseInput::ScrollRight),
+            _ => Some(MouseInput::ScrollLeft),
+        };
+        vertical.into_iter().chain(horizontal)
+    }
+}
+
+// (input, legend, x, y, w, h) in key units, the wheel sits between the buttons
+const PANEL_CELLS: [(MouseInput, &str, f32, f32, f32, f32); 9] = [
+    (MouseInput::Left, "Left", 0., 0., 1., 3.),
+    (MouseInput::ScrollUp, "↑", 1., 0., 1., 1.),
+    (MouseInput::Middle, "Mid", 1., 1., 1., 1.),
+    (MouseInput::ScrollDown, "↓", 1., 2., 1., 1.),
+    (MouseInput::Right, "Right", 2., 0., 1., 3.),
+    (MouseInput::ScrollLeft, "←", 0., 3., 1.5, 1.),
+    (MouseInput::ScrollRight, "→", 1.5, 3., 1.5, 1.),
+    (MouseInput::Back, "Back", 0., 4., 1.5, 1.),
+    (MouseInput::Forward, "Fwd", 1.5, 4., 1.5, 1.),
+];
+
+pub fn panel_size() -> Vec2 {
+    Vec2::new(3., 5.) * KEY_UNIT - Vec2::splat(KEY_GAP)
+}
+
+pub struct MousePanel {
+    // [0, 1], the hue of the color
+    hue: f32,
+}
+
+impl MousePanel {
+    pub fn new(hue: f32) -> Self {
+        Self { hue }
+    }
+
+    pub fn draw(&self, map: &PressTimesMap, ui: &mut Ui) {
+        let (rect, _) = ui.allocate_exact_size(panel_size(), Sense::hover());
+        for (input, legend, x, y, w, h) in PANEL_CELLS {
+            let cell = Rect::from_min_size(
+                rect.min + Vec2::new(x, y) * KEY_UNIT,
+                Vec2::new(w, h) * KEY_UNIT - Vec2::splat(KEY_GAP),
+            );
+            let mut cell = KeyBox::new(
+                cell,
+                KeyTextsLayout::Center1(legend.to_string()),
+                None,
+                map.get_mouse_times(input),
+                self.hue,
+            );
+            cell.ui(ui, ui.id().with(("mouse", input)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wheel_events_count_one_notch_per_direction() {
+        let inputs: Vec<_> = MouseInput::from_wheel(-3, 1).collect();
+        assert!(inputs == [MouseInput::ScrollUp, MouseInput::ScrollLeft]);
+        assert_eq!(MouseInput::from_wheel(0, 0).count(), 0);
+    }
+}
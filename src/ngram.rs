@@ -0,0 +1,205 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, SystemTime},
+};
+
+use crate::press_time_map::{is_modifier, key_from_id, key_to_id};
+
+pub const DEFAULT_IDLE_GAP_MS: u64 = 1000;
+
+/// Counts of consecutive presses, modifiers are skipped so that Shift does not
+/// split a bigram
+#[derive(Clone)]
+pub struct NgramStats {
+    // a pause longer than this starts a new sequence
+    pub idle_gap: Duration,
+    bigrams: HashMap<[rdev::Key; 2], u32>,
+    trigrams: HashMap<[rdev::Key; 3], u32>,
+    // last two keys of the current sequence, oldest first
+    recent: Vec<rdev::Key>,
+    last_press: Option<SystemTime>,
+}
+
+impl NgramStats {
+    pub fn new() -> Self {
+        Self {
+            idle_gap: Duration::from_millis(DEFAULT_IDLE_GAP_MS),
+            bigrams: HashMap::new(),
+            trigrams: HashMap::new(),
+            recent: Vec::new(),
+            last_press: None,
+        }
+    }
+
+    pub fn key_press(&mut self, key: rdev::Key, time: SystemTime) {
+        if is_modifier(key) {
+            return;
+        }
+
+        let idle = self.last_press.is_none_or(|last| {
+            time.duration_since(last)
+                .map_or(true, |gap| gap > self.idle_gap)
+        });
+        if idle {
+            self.recent.clear();
+        }
+        self.last_press = Some(time);
+
+        if let [.., previous] = self.recent[..] {
+            *self.bigrams.entry([previous, key]).or_insert(0) += 1;
+        }
+        if let [first, second] = self.recent[..] {
+            *self.trigrams.entry([first, second, key]).or_insert(0) += 1;
+        }
+        self.recent.push(key);
+        if self.recent.len() > 2 {
+            self.recent.remove(0);
+        }
+    }
+
+    pub fn bigrams(&self) -> impl Iterator<Item = ([rdev::Key; 2], u32)> + '_ {
+        self.bigrams.iter().map(|(&keys, &times)| (keys, times))
+    }
+
+    pub fn top_bigrams(&self, count: usize) -> Vec<([rdev::Key; 2], u32)> {
+        top(&self.bigrams, count)
+    }
+
+    pub fn top_trigrams(&self, count: usize) -> Vec<([rdev::Key; 3], u32)> {
+        top(&self.trigrams, count)
+    }
+
+    /// Keys most often pressed right after `key`, most frequent first
+    pub fn followers(&self, key: rdev::Key, count: usize) -> Vec<(rdev::Key, u32)> {
+        let mut followers: Vec<_> = self
+            .bigrams
+            .iter()
+            .filter(|([first, _], _)| *first == key)
+            .map(|(&[_, second], &times)| (second, times))
+            .collect();
+        followers.sort_by(|a, b| {
+            b.1.cmp(&a.1)
+                .then_with(|| key_to_id(a.0).cmp(&key_to_id(b.0)))
+        });
+        followers.truncate(count);
+        followers
+    }
+
+    /// Counts as if every key had been `remap(key)`, the sequence in progress
+    /// is dropped
+    pub fn remapped(&self, remap: impl Fn(rdev::Key) -> rdev::Key) -> NgramStats {
+        let mut remapped = NgramStats {
+            idle_gap: self.idle_gap,
+            ..NgramStats::new()
+        };
+        for (keys, &count) in &self.bigrams {
+            *remapped.bigrams.entry(keys.map(&remap)).or_insert(0) += count;
+        }
+        for (keys, &count) in &self.trigrams {
+            *remapped.trigrams.entry(keys.map(&remap)).or_insert(0) += count;
+        }
+        remapped
+    }
+
+    pub fn clear(&mut self) {
+        self.bigrams.clear();
+        self.trigrams.clear();
+        self.recent.clear();
+        self.last_press = None;
+    }
+
+    /// Bigrams and trigrams as key id sequences
+    pub fn persisted_entries(&self) -> Vec<(Vec<String>, u32)> {
+        let bigrams = self
+            .bigrams
+            .iter()
+            .map(|(keys, &count)| (keys.map(key_to_id).to_vec(), count));
+        let trigrams = self
+            .trigrams
+            .iter()
+            .map(|(keys, &count)| (keys.map(key_to_id).to_vec(), count));
+        let mut entries: Vec<_> = bigrams.chain(trigrams).collect();
+        entries.sort();
+        entries
+    }
+
+    pub fn restore_entries(&mut self, entries: Vec<(Vec<String>, u32)>) {
+        for (ids, count) in entries {
+            let Some(keys) = ids
+                .iter()
+                .map(|id| key_from_id(id))
+                .collect::<Option<Vec<_>>>()
+            else {
+                continue;
+            };
+            match keys[..] {
+                [first, second] => {
+                    self.bigrams.insert([first, second], count);
+                }
+                [first, second, third] => {
+                    self.trigrams.insert([first, second, third], count);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn top<const N: usize>(
+    counts: &HashMap<[rdev::Key; N], u32>,
+    count: usize,
+) -> Vec<([rdev::Key; N], u32)> {
+    let mut entries: Vec<_> = counts.iter().map(|(&keys, &times)| (keys, times)).collect();
+    entries.sort_by(|a, b| {
+        b.1.cmp(&a.1)
+            .then_with(|| a.0.map(key_to_id).cmp(&b.0.map(key_to_id)))
+    });
+    entries.truncate(count);
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(ms: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn idle_gap_breaks_sequences() {
+        let mut stats = NgramStats::new();
+        stats.key_press(rdev::Key::KeyT, at(0));
+        stats.key_press(rdev::Key::ShiftLeft, at(50));
+        stats.key_press(rdev::Key::KeyH, at(100));
+        stats.key_press(rdev::Key::KeyE, at(200));
+        stats.key_press(rdev::Key::KeyT, at(5000));
+        stats.key_press(rdev::Key::KeyH, at(5100));
+
+        assert_eq!(
+            stats.top_bigrams(1),
+            [([rdev::Key::KeyT, rdev::Key::KeyH], 2)]
+        );
+        assert_eq!(stats.top_trigrams(5).len(), 1);
+        assert_eq!(stats.followers(rdev::Key::KeyH, 3), [(rdev::Key::KeyE, 1)]);
+    }
+
+    #[test]
+    fn ngrams_survive_persistence() {
+        let mut stats = NgramStats::new();
+        for (index, key) in [rdev::Key::KeyA, rdev::Key::KeyB, rdev::Key::KeyC]
+            .into_iter()
+            .enumerate()
+        {
+            stats.key_press(key, at(index as u64 * 100));
+        }
+
+        let mut restored = NgramStats::new();
+        restored.restore_entries(stats.persisted_entries());
+        assert_eq!(restored.top_bigrams(5).len(), 2);
+        assert_eq!(
+            restored.top_trigrams(1),
+            [([rdev::Key::KeyA, rdev::Key::KeyB, rdev::Key::KeyC], 1)]
+        );
+    }
+}
@@ -1,39 +1,416 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::{Duration, SystemTime},
+};
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, TimeDelta};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    activity::PunchCard,
+    chord::{ChordStats, Modifiers},
+    history::{History, PersistedBucket, Tally},
+    mouse::MouseInput,
+    ngram::NgramStats,
+    speed::{self, Speed},
+};
+
+// JIS keys have no `rdev::Key` variant, they are counted under their X11 keycodes
+// and presses from other platforms are mapped onto them
+pub const KEY_YEN: rdev::Key = rdev::Key::Unknown(132);
+pub const KEY_RO: rdev::Key = rdev::Key::Unknown(97);
+pub const KEY_HENKAN: rdev::Key = rdev::Key::Unknown(100);
+pub const KEY_MUHENKAN: rdev::Key = rdev::Key::Unknown(102);
+pub const KEY_KANA: rdev::Key = rdev::Key::Unknown(101);
+
+// width of the buckets hold durations are sorted into
+const HOLD_BUCKET_MS: u64 = 10;
+// a release this long after the press was most likely missed, e.g. while paused
+const MAX_HOLD: Duration = Duration::from_secs(60);
+// typed keys remembered for Backspace and Delete to attribute corrections to
+const MAX_UNDO_DEPTH: usize = 64;
+
+/// How long a key is held down between its press and release
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct HoldStats {
+    pub count: u32,
+    pub total_ms: u64,
+    // number of holds per bucket index
+    buckets: BTreeMap<u64, u32>,
+}
+
+impl HoldStats {
+    fn record(&mut self, duration: Duration) {
+        let ms = duration.as_millis() as u64;
+        self.count += 1;
+        self.total_ms += ms;
+        *self.buckets.entry(ms / HOLD_BUCKET_MS).or_insert(0) += 1;
+    }
+
+    pub fn mean_ms(&self) -> u64 {
+        self.total_ms
+            .checked_div(u64::from(self.count))
+            .unwrap_or(0)
+    }
+
+    /// Upper edge of the bucket holding the 95th percentile
+    pub fn p95_ms(&self) -> u64 {
+        let target = (u64::from(self.count) * 95).div_ceil(100);
+        let mut seen = 0;
+        for (&bucket, &count) in &self.buckets {
+            seen += u64::from(count);
+            if seen >= target {
+                return (bucket + 1) * HOLD_BUCKET_MS;
+            }
+        }
+        0
+    }
+}
 
 pub struct PressTimesMap {
     pub map: HashMap<rdev::Key, u32>,
+    // characters produced by the presses, lowercased, as reported by the OS layout
+    pub chars: HashMap<String, u32>,
+    // presses the OS generated while the key was held down
+    repeats: HashMap<rdev::Key, u32>,
+    holds: HashMap<rdev::Key, HoldStats>,
+    mouse: HashMap<MouseInput, u32>,
+    // presses deleted by Backspace or Delete right after being typed
+    errors: HashMap<rdev::Key, u32>,
+    // the counts above, bucketed by when they were recorded
+    history: History,
+    pub ngrams: NgramStats,
+    pub chords: ChordStats,
+    pub punch_card: PunchCard,
+    // keys currently held down and when they were pressed
+    pressed_at: HashMap<rdev::Key, SystemTime>,
+    // last press that was not an auto-repeat
+    last_press: Option<SystemTime>,
+    // keys typed since the cursor last jumped, in text order
+    typed: Vec<rdev::Key>,
+    // how many of the typed keys are right of the cursor, after arrow keys
+    after_cursor: usize,
 }
 
 impl PressTimesMap {
     pub fn new() -> Self {
         Self {
             map: HashMap::new(),
+            chars: HashMap::new(),
+            repeats: HashMap::new(),
+            holds: HashMap::new(),
+            mouse: HashMap::new(),
+            errors: HashMap::new(),
+            history: History::default(),
+            ngrams: NgramStats::new(),
+            chords: ChordStats::default(),
+            punch_card: PunchCard::default(),
+            pressed_at: HashMap::new(),
+            last_press: None,
+            typed: Vec::new(),
+            after_cursor: 0,
         }
     }
-    pub fn key_press(&mut self, key: rdev::Key) {
-        // incompatitive cases
-        let key = match key {
-            rdev::Key::Unknown(160) => rdev::Key::F3,
-            rdev::Key::Unknown(177) => rdev::Key::F4,
-            rdev::Key::Unknown(176) => rdev::Key::F5,
-            rdev::Key::Unknown(178) => rdev::Key::F6,
-            rdev::Key::Unknown(62) => rdev::Key::ControlRight,
-            rdev::Key::Unknown(114) => rdev::Key::Insert,
-            rdev::Key::Unknown(115) => rdev::Key::Home,
-            rdev::Key::Unknown(116) => rdev::Key::PageUp,
-            rdev::Key::Unknown(117) => rdev::Key::Delete,
-            rdev::Key::Unknown(119) => rdev::Key::End,
-            rdev::Key::Unknown(121) => rdev::Key::PageDown,
-            rdev::Key::Unknown(105) => rdev::Key::PrintScreen,
-            rdev::Key::Unknown(107) => rdev::Key::ScrollLock,
-            rdev::Key::Unknown(113) => rdev::Key::Pause,
-            _ => key,
+    /// Counts a press, returns whether it was an auto-repeat of a held key
+    pub fn key_press(&mut self, key: rdev::Key, time: SystemTime) -> bool {
+        let key = canonical_key(key);
+
+        // a hold starts at its first press, later ones are auto-repeats
+        let held = |pressed: &SystemTime| {
+            time.duration_since(*pressed)
+                .is_ok_and(|held| held <= MAX_HOLD)
         };
+        if self.pressed_at.get(&key).is_some_and(held) {
+            *self.repeats.entry(key).or_insert(0) += 1;
+            *self.history.at(time).repeats.entry(key).or_insert(0) += 1;
+            return true;
+        }
+
+        self.pressed_at.insert(key, time);
         match self.map.get(&key) {
             Some(v) => self.map.insert(key, v + 1),
             None => self.map.insert(key, 1),
         };
+        let tally = self.history.at(time);
+        *tally.keys.entry(key).or_insert(0) += 1;
+        tally.active_ms += speed::active_gap(self.last_press, time).as_millis() as u64;
+        self.last_press = Some(time);
+        self.ngrams.key_press(key, time);
+        self.punch_card.record(time);
+        false
+    }
+
+    pub fn key_release(&mut self, key: rdev::Key, time: SystemTime) {
+        let key = canonical_key(key);
+        let Some(pressed) = self.pressed_at.remove(&key) else {
+            return;
+        };
+        if let Ok(held) = time.duration_since(pressed) {
+            if held <= MAX_HOLD {
+                self.holds.entry(key).or_default().record(held);
+            }
+        }
+    }
+
+    /// Follows the typed text to attribute corrections, every press is passed,
+    /// auto-repeats included. A Backspace counts an error on the key that typed
+    /// the character before the cursor and a Delete on the one after it. The
+    /// left and right arrows move the cursor within the typed keys, while any
+    /// other key or a click moves it somewhere they can no longer be followed
+    pub fn correction_press(
+        &mut self,
+        key: rdev::Key,
+        modifiers: Modifiers,
+        types_text: bool,
+        time: SystemTime,
+    ) {
+        let key = canonical_key(key);
+        let plain = modifiers.is_empty() || modifiers.is_shift_only();
+        if is_modifier(key) {
+            return;
+        }
+        let cursor = self.typed.len() - self.after_cursor;
+        match key {
+            rdev::Key::Backspace if plain => {
+                if cursor > 0 {
+                    let deleted = self.typed.remove(cursor - 1);
+                    self.count_error(deleted, time);
+                }
+                return;
+            }
+            // text right of the typed keys is unknown, it is deleted unblamed
+            rdev::Key::Delete if plain => {
+                if self.after_cursor > 0 {
+                    let deleted = self.typed.remove(cursor);
+                    self.after_cursor -= 1;
+                    self.count_error(deleted, time);
+                }
+                return;
+            }
+            // shifted arrows select text instead
+            rdev::Key::LeftArrow if modifiers.is_empty() && cursor > 0 => {
+                self.after_cursor += 1;
+                return;
+            }
+            rdev::Key::RightArrow if modifiers.is_empty() && self.after_cursor > 0 => {
+                self.after_cursor -= 1;
+                return;
+            }
+            _ => {}
+        }
+        if plain && (types_text || key == rdev::Key::Space) {
+            if self.typed.len() == MAX_UNDO_DEPTH {
+                self.typed.remove(0);
+                self.after_cursor = self.after_cursor.min(self.typed.len());
+            }
+            let cursor = self.typed.len() - self.after_cursor;
+            self.typed.insert(cursor, key);
+            return;
+        }
+        self.forget_typed();
     }
+
+    fn count_error(&mut self, key: rdev::Key, time: SystemTime) {
+        *self.errors.entry(key).or_insert(0) += 1;
+        *self.history.at(time).errors.entry(key).or_insert(0) += 1;
+    }
+
+    fn forget_typed(&mut self) {
+        self.typed.clear();
+        self.after_cursor = 0;
+    }
+
+    pub fn get_error_times(&self, key: rdev::Key) -> u32 {
+        self.errors.get(&key).copied().unwrap_or(0)
+    }
+
+    /// Share of presses, auto-repeats included, that were Backspace or Delete
+    pub fn correction_rate(&self) -> f32 {
+        let corrections: u64 = [rdev::Key::Backspace, rdev::Key::Delete]
+            .into_iter()
+            .map(|key| u64::from(self.get_key_times(key) + self.get_repeat_times(key)))
+            .sum();
+        match self.total_presses() + self.total_repeats() {
+            0 => 0.,
+            total => corrections as f32 / total as f32,
+        }
+    }
+
+    pub fn mouse_press(&mut self, input: MouseInput, time: SystemTime) {
+        self.forget_typed();
+        *self.mouse.entry(input).or_insert(0) += 1;
+        *self.history.at(time).mouse.entry(input).or_insert(0) += 1;
+    }
+
+    pub fn get_mouse_times(&self, input: MouseInput) -> u32 {
+        self.mouse.get(&input).copied().unwrap_or(0)
+    }
+
+    pub fn get_repeat_times(&self, key: rdev::Key) -> u32 {
+        self.repeats.get(&key).copied().unwrap_or(0)
+    }
+
+    pub fn total_repeats(&self) -> u64 {
+        self.repeats.values().map(|&count| u64::from(count)).sum()
+    }
+
+    pub fn hold_stats(&self, key: rdev::Key) -> Option<&HoldStats> {
+        self.holds.get(&key)
+    }
+
+    /// Counts a key pressed together with modifiers, see [`ChordStats::key_press`]
+    pub fn chord_press(&mut self, key: rdev::Key, modifiers: Modifiers, types_text: bool) {
+        self.chords
+            .key_press(canonical_key(key), modifiers, types_text);
+    }
+
+    pub fn char_press(&mut self, name: &str, time: SystemTime) {
+        if !is_text(name) {
+            return;
+        }
+        let name = name.to_lowercase();
+        *self.history.at(time).chars.entry(name.clone()).or_insert(0) += 1;
+        *self.chars.entry(name).or_insert(0) += 1;
+    }
+
+    pub fn get_char_times(&self, name: &str) -> u32 {
+        self.chars.get(name).copied().unwrap_or(0)
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.chars.clear();
+        self.repeats.clear();
+        self.holds.clear();
+        self.mouse.clear();
+        self.errors.clear();
+        self.history.clear();
+        self.ngrams.clear();
+        self.chords.clear();
+        self.punch_card.clear();
+        self.pressed_at.clear();
+        self.last_press = None;
+        self.forget_typed();
+    }
+
+    /// Counts recorded in `[from, to)`, hold times, n-grams, chords and the
+    /// punch card are not bucketed and stay all-time
+    pub fn in_range(&self, from: NaiveDateTime, to: NaiveDateTime) -> PressTimesMap {
+        let tally = self.history.tally(from, to);
+        PressTimesMap {
+            map: tally.keys,
+            chars: tally.chars,
+            repeats: tally.repeats,
+            holds: self.holds.clone(),
+            mouse: tally.mouse,
+            errors: tally.errors,
+            ngrams: self.ngrams.clone(),
+            chords: self.chords.clone(),
+            punch_card: self.punch_card.clone(),
+            ..Self::new()
+        }
+    }
+
+    /// Characters typed between `from` and `to`, from the history buckets
+    pub fn chars_in_range(&self, from: NaiveDateTime, to: NaiveDateTime) -> HashMap<String, u32> {
+        self.history.tally(from, to).chars
+    }
+
+    /// Presses and bigrams as if every key had been pressed at `remap(key)`,
+    /// holds, history and chords are left out
+    pub fn remapped(&self, remap: impl Fn(rdev::Key) -> rdev::Key) -> PressTimesMap {
+        let moved = |counts: &HashMap<rdev::Key, u32>| {
+            let mut moved = HashMap::new();
+            for (&key, &count) in counts {
+                *moved.entry(remap(key)).or_insert(0) += count;
+            }
+            moved
+        };
+        PressTimesMap {
+            map: moved(&self.map),
+            chars: self.chars.clone(),
+            repeats: moved(&self.repeats),
+            mouse: self.mouse.clone(),
+            ngrams: self.ngrams.remapped(&remap),
+            ..Self::new()
+        }
+    }
+
+    pub fn persisted_history(&self) -> Vec<PersistedBucket> {
+        self.history.persisted_buckets()
+    }
+
+    /// Restores saved history, counts saved before history was kept are put
+    /// into the bucket of `start_time`
+    pub fn restore_history(&mut self, buckets: Vec<PersistedBucket>, start_time: NaiveDateTime) {
+        if !buckets.is_empty() {
+            self.history = History::from_persisted_buckets(buckets);
+            return;
+        }
+        let tally = Tally {
+            keys: self.map.clone(),
+            repeats: self.repeats.clone(),
+            chars: self.chars.clone(),
+            mouse: self.mouse.clone(),
+            errors: self.errors.clone(),
+            active_ms: 0,
+        };
+        self.history.seed(start_time, &tally);
+    }
+
+    /// Adds counts kept as history buckets, such as the ones imported into an
+    /// event store
+    pub fn add_history(&mut self, buckets: Vec<PersistedBucket>) {
+        let added = History::from_persisted_buckets(buckets);
+        let tally = added.tally(NaiveDateTime::MIN, NaiveDateTime::MAX);
+        let add_keys = |counts: &mut HashMap<rdev::Key, u32>, added: HashMap<rdev::Key, u32>| {
+            for (key, count) in added {
+                *counts.entry(key).or_insert(0) += count;
+            }
+        };
+        add_keys(&mut self.map, tally.keys);
+        add_keys(&mut self.repeats, tally.repeats);
+        add_keys(&mut self.errors, tally.errors);
+        for (name, count) in tally.chars {
+            *self.chars.entry(name).or_insert(0) += count;
+        }
+        for (input, count) in tally.mouse {
+            *self.mouse.entry(input).or_insert(0) += count;
+        }
+        self.history.merge(added);
+    }
+
+    /// Characters typed and typing time of each day in `[from, to]`
+    pub fn daily_speeds(&self, from: NaiveDate, to: NaiveDate) -> Vec<(NaiveDate, Speed)> {
+        self.history
+            .daily(from, to)
+            .into_iter()
+            .map(|(day, tally)| {
+                let speed = Speed {
+                    chars: tally.chars.values().map(|&count| u64::from(count)).sum(),
+                    active: Duration::from_millis(tally.active_ms),
+                };
+                (day, speed)
+            })
+            .collect()
+    }
+
+    /// Presses in each hour of `day`, only exact for the last week as older
+    /// hours are rolled up into the bucket of their day
+    pub fn hourly_presses(&self, day: NaiveDate) -> [u64; 24] {
+        let start = day.and_time(NaiveTime::MIN);
+        std::array::from_fn(|hour| {
+            let from = start + TimeDelta::hours(hour as i64);
+            self.history
+                .tally(from, from + TimeDelta::hours(1))
+                .keys
+                .values()
+                .map(|&count| u64::from(count))
+                .sum()
+        })
+    }
+
     pub fn get_key_times(&self, key: rdev::Key) -> u32 {
         match self.map.get(&key) {
             Some(&v) => v,
@@ -62,18 +439,154 @@ impl PressTimesMap {
                 map.insert(key, count);
             }
         }
-        Self { map }
+        Self { map, ..Self::new() }
+    }
+
+    pub fn persisted_char_entries(&self) -> Vec<(String, u32)> {
+        let mut entries: Vec<_> = self
+            .chars
+            .iter()
+            .map(|(name, &count)| (name.clone(), count))
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        entries
+    }
+
+    pub fn persisted_repeat_entries(&self) -> Vec<(String, u32)> {
+        let mut entries: Vec<_> = self
+            .repeats
+            .iter()
+            .map(|(&key, &count)| (key_to_id(key), count))
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        entries
+    }
+
+    pub fn restore_repeat_entries(&mut self, entries: Vec<(String, u32)>) {
+        self.repeats = entries
+            .into_iter()
+            .filter_map(|(id, count)| Some((key_from_id(&id)?, count)))
+            .collect();
+    }
+
+    pub fn persisted_error_entries(&self) -> Vec<(String, u32)> {
+        let mut entries: Vec<_> = self
+            .errors
+            .iter()
+            .map(|(&key, &count)| (key_to_id(key), count))
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        entries
+    }
+
+    pub fn restore_error_entries(&mut self, entries: Vec<(String, u32)>) {
+        self.errors = entries
+            .into_iter()
+            .filter_map(|(id, count)| Some((key_from_id(&id)?, count)))
+            .collect();
+    }
+
+    pub fn persisted_mouse_entries(&self) -> Vec<(MouseInput, u32)> {
+        let mut entries: Vec<_> = self
+            .mouse
+            .iter()
+            .map(|(&input, &count)| (input, count))
+            .collect();
+        entries.sort();
+        entries
+    }
+
+    pub fn restore_mouse_entries(&mut self, entries: Vec<(MouseInput, u32)>) {
+        self.mouse = entries.into_iter().collect();
+    }
+
+    pub fn persisted_hold_entries(&self) -> Vec<(String, HoldStats)> {
+        let mut entries: Vec<_> = self
+            .holds
+            .iter()
+            .map(|(&key, stats)| (key_to_id(key), stats.clone()))
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        entries
+    }
+
+    pub fn restore_hold_entries(&mut self, entries: Vec<(String, HoldStats)>) {
+        self.holds = entries
+            .into_iter()
+            .filter_map(|(id, stats)| Some((key_from_id(&id)?, stats)))
+            .collect();
     }
 }
 
-fn key_to_id(key: rdev::Key) -> String {
+/// Whether a name reported for a key press is text, control characters and
+/// whitespace are counted by their keys instead
+pub fn is_text(name: &str) -> bool {
+    !name.is_empty() && !name.chars().any(|c| c.is_control() || c.is_whitespace())
+}
+
+pub fn is_modifier(key: rdev::Key) -> bool {
+    matches!(
+        key,
+        rdev::Key::Alt
+            | rdev::Key::AltGr
+            | rdev::Key::ControlLeft
+            | rdev::Key::ControlRight
+            | rdev::Key::MetaLeft
+            | rdev::Key::MetaRight
+            | rdev::Key::ShiftLeft
+            | rdev::Key::ShiftRight
+            | rdev::Key::Function
+    )
+}
+
+/// Maps keycodes rdev does not know onto the key they are counted under
+fn canonical_key(key: rdev::Key) -> rdev::Key {
+    // incompatitive cases
+    match key {
+        rdev::Key::Unknown(160) => rdev::Key::F3,
+        rdev::Key::Unknown(177) => rdev::Key::F4,
+        rdev::Key::Unknown(176) => rdev::Key::F5,
+        rdev::Key::Unknown(178) => rdev::Key::F6,
+        rdev::Key::Unknown(62) => rdev::Key::ControlRight,
+        rdev::Key::Unknown(114) => rdev::Key::Insert,
+        rdev::Key::Unknown(115) => rdev::Key::Home,
+        rdev::Key::Unknown(116) => rdev::Key::PageUp,
+        rdev::Key::Unknown(117) => rdev::Key::Delete,
+        rdev::Key::Unknown(119) => rdev::Key::End,
+        rdev::Key::Unknown(121) => rdev::Key::PageDown,
+        rdev::Key::Unknown(105) => rdev::Key::PrintScreen,
+        rdev::Key::Unknown(107) => rdev::Key::ScrollLock,
+        rdev::Key::Unknown(113) => rdev::Key::Pause,
+        #[cfg(target_os = "windows")]
+        rdev::Key::Unknown(28) => KEY_HENKAN,
+        #[cfg(target_os = "windows")]
+        rdev::Key::Unknown(29) => KEY_MUHENKAN,
+        #[cfg(target_os = "windows")]
+        rdev::Key::Unknown(21) => KEY_KANA,
+        #[cfg(target_os = "macos")]
+        rdev::Key::Unknown(93) => KEY_YEN,
+        #[cfg(target_os = "macos")]
+        rdev::Key::Unknown(94) => KEY_RO,
+        // Eisu already reports the keycode of Muhenkan, only Kana needs mapping
+        #[cfg(target_os = "macos")]
+        rdev::Key::Unknown(104) => KEY_KANA,
+        _ => key,
+    }
+}
+
+pub fn key_to_id(key: rdev::Key) -> String {
     match key {
+        KEY_YEN => "Yen".to_string(),
+        KEY_RO => "Ro".to_string(),
+        KEY_HENKAN => "Henkan".to_string(),
+        KEY_MUHENKAN => "Muhenkan".to_string(),
+        KEY_KANA => "Kana".to_string(),
         rdev::Key::Unknown(code) => format!("Unknown:{code}"),
         _ => format!("{key:?}"),
     }
 }
 
-fn key_from_id(id: &str) -> Option<rdev::Key> {
+pub fn key_from_id(id: &str) -> Option<rdev::Key> {
     if let Some(code) = id.strip_prefix("Unknown:") {
         return code.parse().ok().map(rdev::Key::Unknown);
     }
@@ -107,8 +620,27 @@ fn key_from_id(id: &str) -> Option<rdev::Key> {
         "F8" => rdev::Key::F8,
         "F9" => rdev::Key::F9,
         "Function" => rdev::Key::Function,
+        "Henkan" => KEY_HENKAN,
         "Home" => rdev::Key::Home,
         "Insert" => rdev::Key::Insert,
+        "IntlBackslash" => rdev::Key::IntlBackslash,
+        "Kana" => KEY_KANA,
+        "Kp0" => rdev::Key::Kp0,
+        "Kp1" => rdev::Key::Kp1,
+        "Kp2" => rdev::Key::Kp2,
+        "Kp3" => rdev::Key::Kp3,
+        "Kp4" => rdev::Key::Kp4,
+        "Kp5" => rdev::Key::Kp5,
+        "Kp6" => rdev::Key::Kp6,
+        "Kp7" => rdev::Key::Kp7,
+        "Kp8" => rdev::Key::Kp8,
+        "Kp9" => rdev::Key::Kp9,
+        "KpDelete" => rdev::Key::KpDelete,
+        "KpDivide" => rdev::Key::KpDivide,
+        "KpMinus" => rdev::Key::KpMinus,
+        "KpMultiply" => rdev::Key::KpMultiply,
+        "KpPlus" => rdev::Key::KpPlus,
+        "KpReturn" => rdev::Key::KpReturn,
         "KeyA" => rdev::Key::KeyA,
         "KeyB" => rdev::Key::KeyB,
         "KeyC" => rdev::Key::KeyC,
@@ -140,6 +672,7 @@ fn key_from_id(id: &str) -> Option<rdev::Key> {
         "MetaLeft" => rdev::Key::MetaLeft,
         "MetaRight" => rdev::Key::MetaRight,
         "Minus" => rdev::Key::Minus,
+        "Muhenkan" => KEY_MUHENKAN,
         "Num0" => rdev::Key::Num0,
         "Num1" => rdev::Key::Num1,
         "Num2" => rdev::Key::Num2,
@@ -150,6 +683,7 @@ fn key_from_id(id: &str) -> Option<rdev::Key> {
         "Num7" => rdev::Key::Num7,
         "Num8" => rdev::Key::Num8,
         "Num9" => rdev::Key::Num9,
+        "NumLock" => rdev::Key::NumLock,
         "PageDown" => rdev::Key::PageDown,
         "PageUp" => rdev::Key::PageUp,
         "Pause" => rdev::Key::Pause,
@@ -158,6 +692,7 @@ fn key_from_id(id: &str) -> Option<rdev::Key> {
         "Return" => rdev::Key::Return,
         "RightArrow" => rdev::Key::RightArrow,
         "RightBracket" => rdev::Key::RightBracket,
+        "Ro" => KEY_RO,
         "ScrollLock" => rdev::Key::ScrollLock,
         "SemiColon" => rdev::Key::SemiColon,
         "ShiftLeft" => rdev::Key::ShiftLeft,
@@ -166,6 +701,181 @@ fn key_from_id(id: &str) -> Option<rdev::Key> {
         "Space" => rdev::Key::Space,
         "Tab" => rdev::Key::Tab,
         "UpArrow" => rdev::Key::UpArrow,
+        "Yen" => KEY_YEN,
         _ => return None,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numpad_counts_survive_persistence() {
+        let mut map = PressTimesMap::new();
+        for key in [rdev::Key::Kp5, rdev::Key::KpReturn, rdev::Key::NumLock] {
+            map.key_press(key, SystemTime::UNIX_EPOCH);
+        }
+
+        let restored = PressTimesMap::from_persisted_entries(map.persisted_entries());
+        assert_eq!(restored.get_key_times(rdev::Key::Kp5), 1);
+        assert_eq!(restored.get_key_times(rdev::Key::KpReturn), 1);
+        assert_eq!(restored.get_key_times(rdev::Key::NumLock), 1);
+    }
+
+    #[test]
+    fn jis_keys_use_named_ids() {
+        assert_eq!(key_to_id(KEY_HENKAN), "Henkan");
+        assert_eq!(key_from_id("Yen"), Some(KEY_YEN));
+        // counts saved before the keys were named still load
+        assert_eq!(key_from_id("Unknown:97"), Some(KEY_RO));
+    }
+
+    #[test]
+    fn characters_ignore_case_and_control_names() {
+        let mut map = PressTimesMap::new();
+        for name in ["r", "R", "\u{8}", " ", "é"] {
+            map.char_press(name, SystemTime::UNIX_EPOCH);
+        }
+
+        assert_eq!(map.get_char_times("r"), 2);
+        assert_eq!(map.get_char_times("é"), 1);
+        assert_eq!(map.chars.len(), 2);
+    }
+
+    #[test]
+    fn auto_repeats_are_counted_apart() {
+        let at = |ms| SystemTime::UNIX_EPOCH + Duration::from_millis(ms);
+        let mut map = PressTimesMap::new();
+        assert!(!map.key_press(rdev::Key::Backspace, at(0)));
+        assert!(map.key_press(rdev::Key::Backspace, at(500)));
+        assert!(map.key_press(rdev::Key::Backspace, at(530)));
+        map.key_release(rdev::Key::Backspace, at(540));
+        assert!(!map.key_press(rdev::Key::Backspace, at(600)));
+
+        assert_eq!(map.get_key_times(rdev::Key::Backspace), 2);
+        assert_eq!(map.get_repeat_times(rdev::Key::Backspace), 2);
+
+        let mut restored = PressTimesMap::new();
+        restored.restore_repeat_entries(map.persisted_repeat_entries());
+        assert_eq!(restored.get_repeat_times(rdev::Key::Backspace), 2);
+    }
+
+    #[test]
+    fn holds_span_from_first_press_to_release() {
+        let at = |ms| SystemTime::UNIX_EPOCH + Duration::from_millis(ms);
+        let mut map = PressTimesMap::new();
+        // auto-repeat presses do not restart the hold
+        map.key_press(rdev::Key::ShiftLeft, at(0));
+        map.key_press(rdev::Key::ShiftLeft, at(500));
+        map.key_release(rdev::Key::ShiftLeft, at(800));
+        map.key_press(rdev::Key::ShiftLeft, at(1000));
+        map.key_release(rdev::Key::ShiftLeft, at(1100));
+        // a release without a press is ignored
+        map.key_release(rdev::Key::ShiftLeft, at(1200));
+
+        let stats = map.hold_stats(rdev::Key::ShiftLeft).unwrap();
+        assert_eq!(stats.count, 2);
+        assert_eq!(stats.mean_ms(), 450);
+        assert_eq!(stats.p95_ms(), 810);
+
+        let mut restored = PressTimesMap::new();
+        restored.restore_hold_entries(map.persisted_hold_entries());
+        assert_eq!(
+            restored.hold_stats(rdev::Key::ShiftLeft).unwrap().total_ms,
+            900
+        );
+    }
+
+    #[test]
+    fn backspace_blames_the_deleted_key() {
+        let mut map = PressTimesMap::new();
+        let mut press = |key, types_text| {
+            map.correction_press(
+                key,
+                Modifiers::default(),
+                types_text,
+                SystemTime::UNIX_EPOCH,
+            );
+        };
+        // "teh", fixed to "the"
+        for key in [rdev::Key::KeyT, rdev::Key::KeyE, rdev::Key::KeyH] {
+            press(key, true);
+        }
+        press(rdev::Key::Backspace, false);
+        press(rdev::Key::Backspace, false);
+        press(rdev::Key::KeyH, true);
+        press(rdev::Key::KeyE, true);
+        // after the cursor jumped the deleted text is unknown
+        press(rdev::Key::Home, false);
+        press(rdev::Key::Backspace, false);
+
+        assert_eq!(map.get_error_times(rdev::Key::KeyH), 1);
+        assert_eq!(map.get_error_times(rdev::Key::KeyE), 1);
+        assert_eq!(map.get_error_times(rdev::Key::KeyT), 0);
+
+        let mut restored = PressTimesMap::new();
+        restored.restore_error_entries(map.persisted_error_entries());
+        assert_eq!(restored.get_error_times(rdev::Key::KeyE), 1);
+    }
+
+    #[test]
+    fn delete_blames_the_key_after_the_cursor() {
+        let mut map = PressTimesMap::new();
+        let mut press = |key, types_text| {
+            map.correction_press(
+                key,
+                Modifiers::default(),
+                types_text,
+                SystemTime::UNIX_EPOCH,
+            );
+        };
+        // "cat", the cursor moved back before "a" and "a" deleted forward
+        for key in [rdev::Key::KeyC, rdev::Key::KeyA, rdev::Key::KeyT] {
+            press(key, true);
+        }
+        press(rdev::Key::LeftArrow, false);
+        press(rdev::Key::LeftArrow, false);
+        press(rdev::Key::Delete, false);
+        // "o" typed in its place, Backspace then deletes it
+        press(rdev::Key::KeyO, true);
+        press(rdev::Key::Backspace, false);
+        // "t" deleted forward, past the typed keys nothing is known
+        press(rdev::Key::RightArrow, false);
+        press(rdev::Key::LeftArrow, false);
+        press(rdev::Key::Delete, false);
+        press(rdev::Key::Delete, false);
+
+        assert_eq!(map.get_error_times(rdev::Key::KeyA), 1);
+        assert_eq!(map.get_error_times(rdev::Key::KeyO), 1);
+        assert_eq!(map.get_error_times(rdev::Key::KeyT), 1);
+        assert_eq!(map.get_error_times(rdev::Key::KeyC), 0);
+    }
+
+    #[test]
+    fn typing_time_leaves_out_idle_gaps() {
+        use chrono::TimeZone;
+
+        let noon = chrono::Local
+            .with_ymd_and_hms(2026, 10, 16, 12, 0, 0)
+            .unwrap();
+        let at = |ms| SystemTime::from(noon) + Duration::from_millis(ms);
+        let mut map = PressTimesMap::new();
+        for (ms, key, name) in [
+            (0, rdev::Key::KeyH, "h"),
+            (200, rdev::Key::KeyI, "i"),
+            (10_000, rdev::Key::KeyY, "y"),
+            (10_300, rdev::Key::KeyO, "o"),
+        ] {
+            map.key_press(key, at(ms));
+            map.char_press(name, at(ms));
+        }
+
+        let day = noon.date_naive();
+        let [(_, speed)] = map.daily_speeds(day, day)[..] else {
+            panic!("expected one day");
+        };
+        assert_eq!(speed.chars, 4);
+        assert_eq!(speed.active, Duration::from_millis(500));
+    }
+}
@@ -0,0 +1,137 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::storage;
+
+/// Profile kept in the app data directory itself, where the counts were
+/// stored before there were profiles
+pub const DEFAULT_PROFILE: &str = "Default";
+// the other profiles each have a directory in here
+const PROFILES_DIR: &str = "profiles";
+// name of the profile in use, loaded at startup
+const ACTIVE_PROFILE_FILE: &str = "active-profile";
+const MAX_NAME_LENGTH: usize = 40;
+
+/// Directory holding the state file, backups and events of `profile`
+pub fn profile_dir(data_dir: &Path, profile: &str) -> PathBuf {
+    if profile == DEFAULT_PROFILE {
+        data_dir.to_path_buf()
+    } else {
+        data_dir.join(PROFILES_DIR).join(profile)
+    }
+}
+
+/// Names of the profiles, the default one first and the others sorted
+pub fn list_profiles(data_dir: &Path) -> Vec<String> {
+    let mut names: Vec<_> = fs::read_dir(data_dir.join(PROFILES_DIR))
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            if !entry.file_type().ok()?.is_dir() {
+                return None;
+            }
+            let name = entry.file_name().into_string().ok()?;
+            check_name(&name).is_ok().then_some(name)
+        })
+        .collect();
+    names.sort();
+    names.insert(0, DEFAULT_PROFILE.to_string());
+    names
+}
+
+/// Why `name` cannot name a profile, it becomes a directory name
+pub fn check_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Enter a name".to_string());
+    }
+    if name.trim() != name {
+        return Err("The name cannot start or end with a space".to_string());
+    }
+    if name.chars().count() > MAX_NAME_LENGTH {
+        return Err(format!("Use at most {MAX_NAME_LENGTH} characters"));
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_'))
+    {
+        return Err("Use letters, digits, spaces, - and _ only".to_string());
+    }
+    if name.eq_ignore_ascii_case(DEFAULT_PROFILE) {
+        return Err(format!("{DEFAULT_PROFILE} is taken"));
+    }
+    Ok(())
+}
+
+/// Creates the directory of a new profile, the error is meant for the UI
+pub fn create_profile(data_dir: &Path, name: &str) -> Result<(), String> {
+    check_name(name)?;
+    // profile directories may live on a case-insensitive filesystem
+    if list_profiles(data_dir)
+        .iter()
+        .any(|profile| profile.to_lowercase() == name.to_lowercase())
+    {
+        return Err(format!("A profile named {name} already exists"));
+    }
+    fs::create_dir_all(profile_dir(data_dir, name))
+        .map_err(|err| format!("The profile could not be created: {err}"))
+}
+
+/// Profile in use when the app last ran, the default one if it is gone
+pub fn active_profile(data_dir: &Path) -> String {
+    let name = fs::read_to_string(data_dir.join(ACTIVE_PROFILE_FILE)).unwrap_or_default();
+    let name = name.trim();
+    if list_profiles(data_dir)
+        .iter()
+        .any(|profile| profile == name)
+    {
+        name.to_string()
+    } else {
+        DEFAULT_PROFILE.to_string()
+    }
+}
+
+pub fn set_active_profile(data_dir: &Path, name: &str) -> io::Result<()> {
+    fs::create_dir_all(data_dir)?;
+    storage::write_atomically(&data_dir.join(ACTIVE_PROFILE_FILE), name.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profiles_are_listed_and_remembered() {
+        let dir =
+            std::env::temp_dir().join(format!("keyboard-heatmap-profiles-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(list_profiles(&dir), [DEFAULT_PROFILE]);
+        assert_eq!(active_profile(&dir), DEFAULT_PROFILE);
+
+        create_profile(&dir, "Home split").unwrap();
+        create_profile(&dir, "Laptop").unwrap();
+        assert!(create_profile(&dir, "laptop").is_err());
+        assert_eq!(
+            list_profiles(&dir),
+            [DEFAULT_PROFILE, "Home split", "Laptop"]
+        );
+        assert_eq!(profile_dir(&dir, DEFAULT_PROFILE), dir);
+
+        set_active_profile(&dir, "Laptop").unwrap();
+        assert_eq!(active_profile(&dir), "Laptop");
+        fs::remove_dir_all(profile_dir(&dir, "Laptop")).unwrap();
+        assert_eq!(active_profile(&dir), DEFAULT_PROFILE);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn names_must_make_plain_directory_names() {
+        assert!(check_name("Work laptop").is_ok());
+        for name in ["", " padded", "../up", "a/b", "default"] {
+            assert!(check_name(name).is_err(), "{name:?}");
+        }
+    }
+}
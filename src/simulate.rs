@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::logical_layout::{character_legends, LogicalLayout};
+
+/// Custom mapping that types like QWERTY, the starting point for edits
+pub const QWERTY_MAPPING: &str = "qwertyuiop asdfghjkl; zxcvbnm,./";
+
+// keys a custom mapping assigns, in the order its characters are listed
+const MAPPED_KEYS: [rdev::Key; 30] = [
+    rdev::Key::KeyQ,
+    rdev::Key::KeyW,
+    rdev::Key::KeyE,
+    rdev::Key::KeyR,
+    rdev::Key::KeyT,
+    rdev::Key::KeyY,
+    rdev::Key::KeyU,
+    rdev::Key::KeyI,
+    rdev::Key::KeyO,
+    rdev::Key::KeyP,
+    rdev::Key::KeyA,
+    rdev::Key::KeyS,
+    rdev::Key::KeyD,
+    rdev::Key::KeyF,
+    rdev::Key::KeyG,
+    rdev::Key::KeyH,
+    rdev::Key::KeyJ,
+    rdev::Key::KeyK,
+    rdev::Key::KeyL,
+    rdev::Key::SemiColon,
+    rdev::Key::KeyZ,
+    rdev::Key::KeyX,
+    rdev::Key::KeyC,
+    rdev::Key::KeyV,
+    rdev::Key::KeyB,
+    rdev::Key::KeyN,
+    rdev::Key::KeyM,
+    rdev::Key::Comma,
+    rdev::Key::Dot,
+    rdev::Key::Slash,
+];
+
+/// Layout the recorded typing is replayed on
+#[derive(Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum SimulatedLayout {
+    Logical(LogicalLayout),
+    /// characters of the three letter rows from Q to /, spaces are ignored
+    Custom(String),
+}
+
+impl Default for SimulatedLayout {
+    fn default() -> Self {
+        SimulatedLayout::Logical(LogicalLayout::Colemak)
+    }
+}
+
+impl SimulatedLayout {
+    pub fn description(&self) -> &'static str {
+        match self {
+            SimulatedLayout::Logical(layout) => layout.description(),
+            SimulatedLayout::Custom(_) => "Custom",
+        }
+    }
+
+    fn targets(&self) -> Result<Vec<Target>, String> {
+        match self {
+            SimulatedLayout::Logical(layout) => Ok(logical_targets(*layout)),
+            SimulatedLayout::Custom(mapping) => custom_targets(mapping),
+        }
+    }
+}
+
+/// (key, unshifted character, keycap legends) of a key the layout types with
+type Target = (rdev::Key, String, Vec<String>);
+
+fn logical_targets(layout: LogicalLayout) -> Vec<Target> {
+    layout
+        .keys()
+        .filter_map(|key| {
+            let (unshifted, shifted) = layout.characters(key)?;
+            Some((
+                key,
+                unshifted.to_string(),
+                character_legends(unshifted, shifted),
+            ))
+        })
+        .collect()
+}
+
+fn custom_targets(mapping: &str) -> Result<Vec<Target>, String> {
+    let characters: Vec<char> = mapping.chars().filter(|c| !c.is_whitespace()).collect();
+    if characters.len() != MAPPED_KEYS.len() {
+        return Err(format!(
+            "a mapping lists {} characters, found {}",
+            MAPPED_KEYS.len(),
+            characters.len()
+        ));
+    }
+    let mut targets: Vec<Target> = Vec::new();
+    for (key, character) in MAPPED_KEYS.into_iter().zip(characters) {
+        let unshifted = character.to_lowercase().to_string();
+        if targets.iter().any(|(_, taken, _)| *taken == unshifted) {
+            return Err(format!("`{character}` is mapped twice"));
+        }
+        targets.push((key, unshifted, vec![character.to_uppercase().to_string()]));
+    }
+    // keys outside the letter rows keep their QWERTY characters
+    for key in LogicalLayout::Qwerty.keys() {
+        if MAPPED_KEYS.contains(&key) {
+            continue;
+        }
+        let Some((unshifted, shifted)) = LogicalLayout::Qwerty.characters(key) else {
+            continue;
+        };
+        if targets.iter().all(|(_, taken, _)| taken != unshifted) {
+            targets.push((
+                key,
+                unshifted.to_string(),
+                character_legends(unshifted, shifted),
+            ));
+        }
+    }
+    Ok(targets)
+}
+
+/// Where each recorded key would have been pressed on another layout to type
+/// the same characters
+pub struct Simulation {
+    remap: HashMap<rdev::Key, rdev::Key>,
+    pub legends: HashMap<rdev::Key, Vec<String>>,
+}
+
+impl Simulation {
+    /// `typed_with` is the layout the OS used while the typing was recorded
+    pub fn new(typed_with: LogicalLayout, target: &SimulatedLayout) -> Result<Self, String> {
+        let targets = target.targets()?;
+        let remap = typed_with
+            .keys()
+            .filter_map(|key| {
+                let (typed, _) = typed_with.characters(key)?;
+                let (target_key, _, _) = targets
+                    .iter()
+                    .find(|(_, unshifted, _)| unshifted == typed)?;
+                (*target_key != key).then_some((key, *target_key))
+            })
+            .collect();
+        let legends = targets
+            .into_iter()
+            .map(|(key, _, legends)| (key, legends))
+            .collect();
+        Ok(Self { remap, legends })
+    }
+
+    /// Keys typing no character, or one the target lacks, stay in place
+    pub fn key(&self, key: rdev::Key) -> rdev::Key {
+        self.remap.get(&key).copied().unwrap_or(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_move_to_where_the_target_types_their_character() {
+        let colemak = SimulatedLayout::Logical(LogicalLayout::Colemak);
+        let simulation = Simulation::new(LogicalLayout::Qwerty, &colemak).unwrap();
+        assert_eq!(simulation.key(rdev::Key::KeyE), rdev::Key::KeyK);
+        assert_eq!(simulation.key(rdev::Key::KeyA), rdev::Key::KeyA);
+        assert_eq!(simulation.key(rdev::Key::Space), rdev::Key::Space);
+
+        // typing Colemak and replaying it on Colemak changes nothing
+        let same = Simulation::new(LogicalLayout::Colemak, &colemak).unwrap();
+        assert!(same.remap.is_empty());
+    }
+
+    #[test]
+    fn custom_mappings_list_every_letter_key() {
+        let qwerty = SimulatedLayout::Custom(QWERTY_MAPPING.to_string());
+        assert!(Simulation::new(LogicalLayout::Qwerty, &qwerty)
+            .unwrap()
+            .remap
+            .is_empty());
+
+        let swapped = SimulatedLayout::Custom("qwertyuiop asdfghjkl; xzcvbnm,./".to_string());
+        let simulation = Simulation::new(LogicalLayout::Qwerty, &swapped).unwrap();
+        assert_eq!(simulation.key(rdev::Key::KeyZ), rdev::Key::KeyX);
+        assert_eq!(simulation.legends[&rdev::Key::KeyX], ["Z"]);
+
+        let short = SimulatedLayout::Custom("qwerty".to_string());
+        assert!(Simulation::new(LogicalLayout::Qwerty, &short).is_err());
+    }
+}
@@ -0,0 +1,127 @@
+use std::time::{Duration, SystemTime};
+
+use chrono::NaiveDate;
+use egui::{Align2, Color32, FontId, Rect, Sense, Ui, Vec2};
+
+/// A pause longer than this between two presses is not typing time
+pub const IDLE_GAP: Duration = Duration::from_secs(2);
+/// Span the live speed is averaged over
+pub const ROLLING_WINDOW: Duration = Duration::from_secs(60);
+/// Days shown in the daily speed chart, today included
+pub const CHART_DAYS: i64 = 30;
+
+// shorter bursts give wild speeds, they are not shown
+const MIN_ACTIVE: Duration = Duration::from_secs(3);
+// characters per word, as speed tests count them
+const WORD_LENGTH: f64 = 5.;
+
+const CHART_HEIGHT: f32 = 140.;
+const BAR_WIDTH: f32 = 16.;
+const BAR_GAP: f32 = 4.;
+// room for the value above a bar and the date below it
+const BAR_LABEL_HEIGHT: f32 = 16.;
+
+/// Characters typed during some active typing time
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Speed {
+    pub chars: u64,
+    pub active: Duration,
+}
+
+impl Speed {
+    pub fn is_measurable(&self) -> bool {
+        self.active >= MIN_ACTIVE
+    }
+
+    pub fn cpm(&self) -> f64 {
+        if self.active.is_zero() {
+            return 0.;
+        }
+        self.chars as f64 * 60. / self.active.as_secs_f64()
+    }
+
+    pub fn wpm(&self) -> f64 {
+        self.cpm() / WORD_LENGTH
+    }
+}
+
+/// Typing time between a press and the one before it, idle gaps count as none
+pub fn active_gap(previous: Option<SystemTime>, time: SystemTime) -> Duration {
+    previous
+        .and_then(|previous| time.duration_since(previous).ok())
+        .filter(|gap| *gap <= IDLE_GAP)
+        .unwrap_or_default()
+}
+
+/// Bars of the words per minute of each day, oldest first
+pub fn draw_daily_chart(ui: &mut Ui, days: &[(NaiveDate, Speed)]) {
+    let text_color = Color32::from_rgb(32, 5, 64);
+    let bar_color = Color32::from_rgb(60, 110, 220);
+    let width = days.len() as f32 * (BAR_WIDTH + BAR_GAP);
+    let (rect, _) = ui.allocate_exact_size(Vec2::new(width, CHART_HEIGHT), Sense::hover());
+    let painter = ui.painter();
+    let bar_bottom = rect.bottom() - BAR_LABEL_HEIGHT;
+    let max_height = CHART_HEIGHT - 2. * BAR_LABEL_HEIGHT;
+    let max_wpm = days
+        .iter()
+        .filter(|(_, speed)| speed.is_measurable())
+        .map(|(_, speed)| speed.wpm())
+        .fold(0., f64::max);
+
+    for (index, (day, speed)) in days.iter().enumerate() {
+        let x = rect.left() + index as f32 * (BAR_WIDTH + BAR_GAP);
+        let center_x = x + BAR_WIDTH / 2.;
+        if speed.is_measurable() && max_wpm > 0. {
+            let height = max_height * (speed.wpm() / max_wpm) as f32;
+            let bar = Rect::from_min_max(
+                egui::pos2(x, bar_bottom - height),
+                egui::pos2(x + BAR_WIDTH, bar_bottom),
+            );
+            painter.rect_filled(bar, 2., bar_color);
+            painter.text(
+                bar.center_top(),
+                Align2::CENTER_BOTTOM,
+                format!("{:.0}", speed.wpm()),
+                FontId::monospace(9.),
+                text_color,
+            );
+        }
+        // a date under every week, counted back from today
+        if (days.len() - 1 - index).is_multiple_of(7) {
+            painter.text(
+                egui::pos2(center_x, bar_bottom + 3.),
+                Align2::CENTER_TOP,
+                day.format("%m-%d").to_string(),
+                FontId::monospace(9.),
+                text_color,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(ms: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn idle_gaps_are_not_typing_time() {
+        assert_eq!(active_gap(Some(at(0)), at(300)), Duration::from_millis(300));
+        assert_eq!(active_gap(Some(at(0)), at(5000)), Duration::ZERO);
+        assert_eq!(active_gap(None, at(300)), Duration::ZERO);
+    }
+
+    #[test]
+    fn words_are_five_characters() {
+        let speed = Speed {
+            chars: 300,
+            active: Duration::from_secs(60),
+        };
+        assert_eq!(speed.cpm(), 300.);
+        assert_eq!(speed.wpm(), 60.);
+        assert!(!Speed::default().is_measurable());
+    }
+}
@@ -0,0 +1,353 @@
+use std::{
+    fmt, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{Local, NaiveDate, NaiveDateTime};
+use serde_json::Value;
+
+/// Version of the state file layout this build writes
+pub const STATE_VERSION: u32 = 1;
+
+type Migration = fn(&mut Value) -> Result<(), String>;
+
+// `MIGRATIONS[n]` turns a state file of version n into version n + 1
+const MIGRATIONS: [Migration; STATE_VERSION as usize] = [
+    // files written before the version field match the first layout, the
+    // fields they lack take their serde defaults
+    |_| Ok(()),
+];
+
+/// Minutes between two autosaves
+pub const DEFAULT_AUTOSAVE_INTERVAL_MIN: u64 = 5;
+/// Presses since the last save that trigger an autosave before the interval
+pub const DEFAULT_AUTOSAVE_PRESSES: u64 = 1000;
+/// How often the autosave thread checks whether a save is due
+pub const AUTOSAVE_POLL: Duration = Duration::from_secs(1);
+
+/// Snapshots of each kind kept, older ones are deleted
+pub const KEPT_SNAPSHOTS: usize = 7;
+// time a snapshot was taken, in its file name. Snapshots taken within the
+// same second get a counter after it
+const SNAPSHOT_TIME_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+/// Why a copy of the state was taken
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SnapshotKind {
+    /// at the first save of a day
+    Daily,
+    BeforeClear,
+    BeforeRestore,
+}
+
+impl SnapshotKind {
+    const ALL: [SnapshotKind; 3] = [
+        SnapshotKind::Daily,
+        SnapshotKind::BeforeClear,
+        SnapshotKind::BeforeRestore,
+    ];
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            SnapshotKind::Daily => "Daily backup",
+            SnapshotKind::BeforeClear => "Before clearing",
+            SnapshotKind::BeforeRestore => "Before restoring",
+        }
+    }
+
+    fn prefix(&self) -> &'static str {
+        match self {
+            SnapshotKind::Daily => "daily",
+            SnapshotKind::BeforeClear => "before-clear",
+            SnapshotKind::BeforeRestore => "before-restore",
+        }
+    }
+}
+
+/// State file copy in the backup directory
+#[derive(Clone, PartialEq, Debug)]
+pub struct Snapshot {
+    pub path: PathBuf,
+    pub kind: SnapshotKind,
+    pub taken: NaiveDateTime,
+    // orders the snapshots taken within the same second
+    sequence: u32,
+}
+
+impl Snapshot {
+    fn from_path(path: PathBuf) -> Option<Self> {
+        if path.extension()? != "json" {
+            return None;
+        }
+        let stem = path.file_stem()?.to_str()?;
+        let (kind, time) = SnapshotKind::ALL.into_iter().find_map(|kind| {
+            let time = stem.strip_prefix(kind.prefix())?.strip_prefix('-')?;
+            Some((kind, time))
+        })?;
+        let (taken, sequence) = match NaiveDateTime::parse_from_str(time, SNAPSHOT_TIME_FORMAT) {
+            Ok(taken) => (taken, 0),
+            Err(_) => {
+                let (time, sequence) = time.rsplit_once('-')?;
+                (
+                    NaiveDateTime::parse_from_str(time, SNAPSHOT_TIME_FORMAT).ok()?,
+                    sequence.parse().ok()?,
+                )
+            }
+        };
+        Some(Self {
+            path,
+            kind,
+            taken,
+            sequence,
+        })
+    }
+}
+
+/// Snapshots in `dir`, newest first
+pub fn list_snapshots(dir: &Path) -> Vec<Snapshot> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut snapshots: Vec<_> = entries
+        .filter_map(|entry| Snapshot::from_path(entry.ok()?.path()))
+        .collect();
+    snapshots.sort_by_key(|snapshot| std::cmp::Reverse((snapshot.taken, snapshot.sequence)));
+    snapshots
+}
+
+/// Writes a snapshot into `dir` next to the existing ones, only the newest
+/// `KEPT_SNAPSHOTS` of its kind are kept
+pub fn write_snapshot(
+    dir: &Path,
+    kind: SnapshotKind,
+    taken: NaiveDateTime,
+    bytes: &[u8],
+) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let stem = format!("{}-{}", kind.prefix(), taken.format(SNAPSHOT_TIME_FORMAT));
+    // an earlier snapshot of the same second may hold the data to go back to
+    let path = (0..)
+        .map(|sequence| match sequence {
+            0 => dir.join(format!("{stem}.json")),
+            _ => dir.join(format!("{stem}-{sequence}.json")),
+        })
+        .find(|path| !path.exists())
+        .expect("a free snapshot name");
+    write_atomically(&path, bytes)?;
+    for old in list_snapshots(dir)
+        .into_iter()
+        .filter(|snapshot| snapshot.kind == kind)
+        .skip(KEPT_SNAPSHOTS)
+    {
+        fs::remove_file(old.path)?;
+    }
+    Ok(path)
+}
+
+pub fn has_daily_backup(dir: &Path, day: NaiveDate) -> bool {
+    list_snapshots(dir)
+        .iter()
+        .any(|snapshot| snapshot.kind == SnapshotKind::Daily && snapshot.taken.date() == day)
+}
+
+/// Replaces the file at `path` with `bytes`, a crash during the write leaves
+/// either the old or the new file but never a partial one
+pub fn write_atomically(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let temp_path = temp_path(path);
+    {
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+    }
+    fs::rename(&temp_path, path)?;
+    // the rename is only durable once the directory entry is written
+    #[cfg(unix)]
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::File::open(parent)?.sync_all()?;
+    }
+    Ok(())
+}
+
+/// Why a state file could not be read, displayed as the end of a sentence
+/// about the file
+#[derive(Debug)]
+pub enum ReadError {
+    /// the file is fine but this build cannot read its version
+    NewerVersion(u32),
+    Unreadable(String),
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadError::NewerVersion(version) => write!(
+                f,
+                "was written by a newer version of the app (version {version})"
+            ),
+            ReadError::Unreadable(problem) => f.write_str(problem),
+        }
+    }
+}
+
+impl std::error::Error for ReadError {}
+
+impl From<ReadError> for String {
+    fn from(err: ReadError) -> Self {
+        err.to_string()
+    }
+}
+
+/// Reads a state file and migrates it to `STATE_VERSION`, `None` if there is
+/// no file yet. The error tells what is wrong with the file
+pub fn read_versioned(path: &Path) -> Result<Option<Value>, ReadError> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => {
+            return Err(ReadError::Unreadable(format!("could not be read: {err}")));
+        }
+    };
+    let mut value: Value = serde_json::from_slice(&bytes)
+        .map_err(|err| ReadError::Unreadable(format!("is not valid JSON: {err}")))?;
+    migrate(&mut value)?;
+    Ok(Some(value))
+}
+
+fn migrate(value: &mut Value) -> Result<(), ReadError> {
+    if !value.is_object() {
+        return Err(ReadError::Unreadable(
+            "does not hold a JSON object".to_string(),
+        ));
+    }
+    let version = match value.get("version") {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| ReadError::Unreadable(format!("has an invalid version {version}")))?,
+    };
+    if version > STATE_VERSION {
+        return Err(ReadError::NewerVersion(version));
+    }
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(value).map_err(ReadError::Unreadable)?;
+    }
+    value["version"] = STATE_VERSION.into();
+    Ok(())
+}
+
+/// Moves an unreadable file aside, with the time it was found in its name, so
+/// the next save does not overwrite it
+pub fn quarantine(path: &Path) -> io::Result<PathBuf> {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = format!(
+        "{stem}.unreadable-{}.json",
+        Local::now().format("%Y%m%d-%H%M%S")
+    );
+    let target = path.with_file_name(name);
+    fs::rename(path, &target)?;
+    Ok(target)
+}
+
+// next to the target so the rename stays on one filesystem
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn atomic_writes_replace_the_file() {
+        let dir =
+            std::env::temp_dir().join(format!("keyboard-heatmap-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("state.json");
+
+        write_atomically(&path, b"first").unwrap();
+        write_atomically(&path, b"second").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"second");
+        assert!(!temp_path(&path).exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn snapshots_rotate_per_kind() {
+        let dir =
+            std::env::temp_dir().join(format!("keyboard-heatmap-snapshots-{}", std::process::id()));
+        let day = |day: u32| {
+            NaiveDate::from_ymd_opt(2026, 10, day)
+                .unwrap()
+                .and_hms_opt(9, 0, 0)
+                .unwrap()
+        };
+        for day_of_month in 1..=9 {
+            write_snapshot(&dir, SnapshotKind::Daily, day(day_of_month), b"{}").unwrap();
+        }
+        write_snapshot(&dir, SnapshotKind::BeforeClear, day(2), b"{}").unwrap();
+
+        let snapshots = list_snapshots(&dir);
+        let daily: Vec<_> = snapshots
+            .iter()
+            .filter(|snapshot| snapshot.kind == SnapshotKind::Daily)
+            .collect();
+        assert_eq!(daily.len(), KEPT_SNAPSHOTS);
+        assert_eq!(daily[0].taken, day(9));
+        assert_eq!(snapshots.len(), KEPT_SNAPSHOTS + 1);
+        assert!(has_daily_backup(&dir, day(9).date()));
+        assert!(!has_daily_backup(&dir, day(1).date()));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn snapshots_of_the_same_second_are_kept() {
+        let dir = std::env::temp_dir().join(format!(
+            "keyboard-heatmap-same-second-{}",
+            std::process::id()
+        ));
+        let taken = NaiveDate::from_ymd_opt(2026, 10, 17)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap();
+        let first = write_snapshot(&dir, SnapshotKind::BeforeClear, taken, b"1").unwrap();
+        let second = write_snapshot(&dir, SnapshotKind::BeforeClear, taken, b"2").unwrap();
+        assert_ne!(first, second);
+        assert_eq!(fs::read(&first).unwrap(), b"1");
+
+        let snapshots = list_snapshots(&dir);
+        assert_eq!(snapshots.len(), 2);
+        // the newest one first
+        assert_eq!(snapshots[0].path, second);
+        assert_eq!(snapshots[1].taken, taken);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn unversioned_files_are_migrated() {
+        let mut value = serde_json::json!({ "hue": 0.5 });
+        migrate(&mut value).unwrap();
+        assert_eq!(value["version"], STATE_VERSION);
+        assert_eq!(value["hue"], 0.5);
+
+        let mut newer = serde_json::json!({ "version": STATE_VERSION + 1 });
+        assert!(matches!(
+            migrate(&mut newer),
+            Err(ReadError::NewerVersion(version)) if version == STATE_VERSION + 1
+        ));
+        assert!(matches!(
+            migrate(&mut serde_json::json!([1, 2])),
+            Err(ReadError::Unreadable(_))
+        ));
+    }
+}
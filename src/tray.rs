@@ -1,7 +1,7 @@
 use eframe::egui;
 use std::{error::Error, sync::OnceLock};
 use tray_icon::{
-    menu::{Menu, MenuEvent, MenuItem},
+    menu::{CheckMenuItem, Menu, MenuEvent, MenuItem, Submenu},
     Icon, MouseButton, MouseButtonState, TrayIcon, TrayIconBuilder, TrayIconEvent,
 };
 
@@ -10,20 +10,25 @@ const TRAY_ICON_HEIGHT: u32 = 16;
 const TOGGLE_WINDOW_ID: &str = "toggle-window";
 const TOGGLE_RECORDING_ID: &str = "toggle-recording";
 const CLEAR_DATA_ID: &str = "clear-data";
+// followed by the name of the profile
+const PROFILE_ID_PREFIX: &str = "profile:";
 const QUIT_ID: &str = "quit";
+const TOOLTIP: &str = "Keyboard Heatmap";
 static TRAY_COMMAND_SENDER: OnceLock<crossbeam_channel::Sender<TrayCommand>> = OnceLock::new();
 static TRAY_COMMAND_RECEIVER: OnceLock<crossbeam_channel::Receiver<TrayCommand>> = OnceLock::new();
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TrayCommand {
     ToggleWindow,
     ToggleRecording,
+    SwitchProfile(String),
     ClearData,
     Quit,
 }
 
 pub struct TrayController {
     _tray_icon: TrayIcon,
+    profiles_menu: Submenu,
 }
 
 impl TrayController {
@@ -32,23 +37,26 @@ impl TrayController {
         let toggle_window = MenuItem::with_id(TOGGLE_WINDOW_ID, "Show / Hide Window", true, None);
         let toggle_recording =
             MenuItem::with_id(TOGGLE_RECORDING_ID, "Pause / Resume Recording", true, None);
+        let profiles_menu = Submenu::new("Profile", true);
         let clear_data = MenuItem::with_id(CLEAR_DATA_ID, "Clear Data", true, None);
         let quit = MenuItem::with_id(QUIT_ID, "Quit", true, None);
         menu.append(&toggle_window)?;
         menu.append(&toggle_recording)?;
+        menu.append(&profiles_menu)?;
         menu.append(&clear_data)?;
         menu.append(&quit)?;
 
         let tray_icon = TrayIconBuilder::new()
             .with_menu(Box::new(menu))
-            .with_icon(tray_icon_image()?)
+            .with_icon(tray_icon_image(false)?)
             .with_icon_as_template(cfg!(target_os = "macos"))
-            .with_tooltip("Keyboard Heatmap")
+            .with_tooltip(TOOLTIP)
             .with_menu_on_left_click(false)
             .build()?;
 
         Ok(Self {
             _tray_icon: tray_icon,
+            profiles_menu,
         })
     }
 
@@ -74,6 +82,42 @@ impl TrayController {
         }));
     }
 
+    /// Shows `message` on the tray icon until it is called with `None`
+    pub fn set_alert(&self, message: Option<&str>) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let tray_icon = &self._tray_icon;
+        tray_icon.set_icon_with_as_template(
+            Some(tray_icon_image(message.is_some())?),
+            cfg!(target_os = "macos") && message.is_none(),
+        )?;
+        match message {
+            Some(message) => tray_icon.set_tooltip(Some(format!("{TOOLTIP}: {message}")))?,
+            None => tray_icon.set_tooltip(Some(TOOLTIP))?,
+        }
+        // shown next to the icon where the platform supports it
+        tray_icon.set_title(message.map(|_| "Break"));
+        Ok(())
+    }
+
+    /// Lists `profiles` in the profile submenu, `active` checked
+    pub fn set_profiles(
+        &self,
+        profiles: &[String],
+        active: &str,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        while self.profiles_menu.remove_at(0).is_some() {}
+        for profile in profiles {
+            let item = CheckMenuItem::with_id(
+                format!("{PROFILE_ID_PREFIX}{profile}"),
+                profile,
+                true,
+                profile == active,
+                None,
+            );
+            self.profiles_menu.append(&item)?;
+        }
+        Ok(())
+    }
+
     pub fn poll(&self) -> Vec<TrayCommand> {
         let mut commands = Vec::new();
         if let Some(receiver) = TRAY_COMMAND_RECEIVER.get() {
@@ -111,15 +155,20 @@ fn menu_event_command(event: &MenuEvent) -> Option<TrayCommand> {
     } else if event.id().0 == QUIT_ID {
         Some(TrayCommand::Quit)
     } else {
-        None
+        event
+            .id()
+            .0
+            .strip_prefix(PROFILE_ID_PREFIX)
+            .map(|profile| TrayCommand::SwitchProfile(profile.to_string()))
     }
 }
 
-fn tray_icon_image() -> Result<Icon, tray_icon::BadIcon> {
-    Icon::from_rgba(tray_icon_rgba(), TRAY_ICON_WIDTH, TRAY_ICON_HEIGHT)
+fn tray_icon_image(alert: bool) -> Result<Icon, tray_icon::BadIcon> {
+    Icon::from_rgba(tray_icon_rgba(alert), TRAY_ICON_WIDTH, TRAY_ICON_HEIGHT)
 }
 
-fn tray_icon_rgba() -> Vec<u8> {
+/// Keyboard outline, with red keys while an alert is shown
+fn tray_icon_rgba(alert: bool) -> Vec<u8> {
     let mut rgba = vec![0; (TRAY_ICON_WIDTH * TRAY_ICON_HEIGHT * 4) as usize];
 
     for y in 2..14 {
@@ -129,9 +178,14 @@ fn tray_icon_rgba() -> Vec<u8> {
             let alpha = if border || key_area { 255 } else { 0 };
             let shade = if key_area { 70 } else { 40 };
             let index = ((y * TRAY_ICON_WIDTH + x) * 4) as usize;
-            rgba[index] = shade;
-            rgba[index + 1] = shade;
-            rgba[index + 2] = shade;
+            let (red, green, blue) = if alert && key_area {
+                (220, 50, 40)
+            } else {
+                (shade, shade, shade)
+            };
+            rgba[index] = red;
+            rgba[index + 1] = green;
+            rgba[index + 2] = blue;
             rgba[index + 3] = alpha;
         }
     }
@@ -151,13 +205,27 @@ mod tests {
 
     #[test]
     fn tray_icon_pixels_match_expected_size() {
-        let rgba = tray_icon_rgba();
+        let rgba = tray_icon_rgba(false);
         assert_eq!(
             rgba.len(),
             (TRAY_ICON_WIDTH * TRAY_ICON_HEIGHT * 4) as usize
         );
     }
 
+    #[test]
+    fn alert_icon_tints_only_the_key_rows() {
+        let (normal, alert) = (tray_icon_rgba(false), tray_icon_rgba(true));
+        assert_eq!(alert.len(), normal.len());
+        let pixel = |rgba: &[u8], x: u32, y: u32| {
+            let index = ((y * TRAY_ICON_WIDTH + x) * 4) as usize;
+            rgba[index..index + 4].to_vec()
+        };
+        // a key row turns red, the border keeps its shade
+        assert_eq!(pixel(&alert, 5, 5), [220, 50, 40, 255]);
+        assert_eq!(pixel(&normal, 5, 5), [70, 70, 70, 255]);
+        assert_eq!(pixel(&alert, 1, 7), pixel(&normal, 1, 7));
+    }
+
     #[test]
     fn tray_left_click_toggles_window() {
         let event = TrayIconEvent::Click {
@@ -192,5 +260,13 @@ mod tests {
             Some(TrayCommand::ClearData)
         );
         assert_eq!(menu_event_command(&quit_event), Some(TrayCommand::Quit));
+
+        let profile_event = MenuEvent {
+            id: tray_icon::menu::MenuId::new(format!("{PROFILE_ID_PREFIX}Home split")),
+        };
+        assert_eq!(
+            menu_event_command(&profile_event),
+            Some(TrayCommand::SwitchProfile("Home split".to_string()))
+        );
     }
 }
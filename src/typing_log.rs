@@ -1,8 +1,11 @@
-use std::collections::VecDeque;
+use std::{collections::VecDeque, time::SystemTime};
 
 use crate::{
     key_box::KeyTextsLayout,
     keyboard::{self, KeyPreviewSpec, KeyboardType},
+    layout::{self, KeyboardLayout},
+    press_time_map::is_text,
+    speed::{self, Speed},
 };
 
 const DEFAULT_LOG_CAPACITY: usize = 1024;
@@ -10,6 +13,8 @@ const DEFAULT_LOG_CAPACITY: usize = 1024;
 struct LoggedKey {
     key: rdev::Key,
     fallback_name: Option<String>,
+    time: SystemTime,
+    auto_repeat: bool,
 }
 
 pub struct TypingLog {
@@ -29,7 +34,7 @@ impl TypingLog {
         }
     }
 
-    pub fn push_event(&mut self, event: &rdev::Event) {
+    pub fn push_event(&mut self, event: &rdev::Event, auto_repeat: bool) {
         let rdev::EventType::KeyPress(key) = event.event_type else {
             return;
         };
@@ -41,6 +46,8 @@ impl TypingLog {
         self.entries.push_back(LoggedKey {
             key,
             fallback_name: event.name.clone(),
+            time: event.time,
+            auto_repeat,
         });
     }
 
@@ -56,16 +63,37 @@ impl TypingLog {
         self.capacity
     }
 
-    pub fn preview_keycaps(&self, keyboard_type: KeyboardType) -> Vec<KeyPreviewSpec> {
+    /// Speed over the presses of the last `speed::ROLLING_WINDOW` before `now`,
+    /// auto-repeats left out
+    pub fn rolling_speed(&self, now: SystemTime) -> Speed {
+        let since = now - speed::ROLLING_WINDOW;
+        let mut rolling = Speed::default();
+        let mut previous = None;
+        for entry in self
+            .entries
+            .iter()
+            .filter(|entry| !entry.auto_repeat && entry.time >= since)
+        {
+            rolling.active += speed::active_gap(previous, entry.time);
+            if entry.fallback_name.as_deref().is_some_and(is_text) {
+                rolling.chars += 1;
+            }
+            previous = Some(entry.time);
+        }
+        rolling
+    }
+
+    pub fn preview_keycaps(&self, keyboard_type: &KeyboardType) -> Vec<KeyPreviewSpec> {
+        let layout = layout::layout_for(keyboard_type);
         self.entries
             .iter()
-            .map(|entry| keycap_for_event(entry, keyboard_type))
+            .map(|entry| keycap_for_event(entry, layout.as_deref()))
             .collect()
     }
 }
 
-fn keycap_for_event(entry: &LoggedKey, keyboard_type: KeyboardType) -> KeyPreviewSpec {
-    if let Some(spec) = keyboard::key_preview_spec(keyboard_type, entry.key) {
+fn keycap_for_event(entry: &LoggedKey, layout: Option<&KeyboardLayout>) -> KeyPreviewSpec {
+    if let Some(spec) = layout.and_then(|layout| keyboard::key_preview_spec(layout, entry.key)) {
         return spec;
     }
 